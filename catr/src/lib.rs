@@ -1,54 +1,300 @@
 use clap::{App, Arg};
 use std::error::Error;
 use std::fs::File;
-use std::io::{self, BufRead, BufReader};
+use std::io::{self, BufRead, BufReader, Write};
 
 // Type alias
 type MyResult<T> = Result<T, Box<dyn Error>>;
 
 // Add the Debug trait
 // so the struct can use print method?
-#[derive(Debug)]
+/// Options for formatted concatenation.
+///
+/// Start from `Config::default()` and switch options on with the builder
+/// methods, e.g. `Config::default().number_lines(true).show_ends(true)`.
+#[derive(Debug, Clone, PartialEq)]
 pub struct Config {
     files: Vec<String>,
     number_lines: bool,
     number_nonblank_lines: bool,
+    // Display `$` at the end of each line
+    show_ends: bool,
+    // Display tabs as `^I`
+    show_tabs: bool,
+    // Display control and high bytes in caret and M- notation
+    show_nonprinting: bool,
+    // Collapse runs of blank lines into one
+    squeeze_blank: bool,
+    // Restart numbering (and squeezing) with every file
+    per_file_numbering: bool,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            // Read stdin when no file is given
+            files: vec!["-".to_string()],
+            number_lines: false,
+            number_nonblank_lines: false,
+            show_ends: false,
+            show_tabs: false,
+            show_nonprinting: false,
+            squeeze_blank: false,
+            per_file_numbering: false,
+        }
+    }
+}
+
+impl Config {
+    /// Files read by `run`, with `-` for stdin.
+    pub fn files(mut self, files: Vec<String>) -> Self {
+        self.files = files;
+        self
+    }
+
+    /// Number all output lines (`-n`).
+    pub fn number_lines(mut self, yes: bool) -> Self {
+        self.number_lines = yes;
+        self
+    }
+
+    /// Number nonempty output lines (`-b`).
+    pub fn number_nonblank_lines(mut self, yes: bool) -> Self {
+        self.number_nonblank_lines = yes;
+        self
+    }
+
+    /// Display `$` at the end of each line (`-E`).
+    pub fn show_ends(mut self, yes: bool) -> Self {
+        self.show_ends = yes;
+        self
+    }
+
+    /// Display tabs as `^I` (`-T`).
+    pub fn show_tabs(mut self, yes: bool) -> Self {
+        self.show_tabs = yes;
+        self
+    }
+
+    /// Use `^` and `M-` notation for nonprinting bytes (`-v`).
+    pub fn show_nonprinting(mut self, yes: bool) -> Self {
+        self.show_nonprinting = yes;
+        self
+    }
+
+    /// Shorthand for `show_nonprinting`, `show_ends` and `show_tabs` (`-A`).
+    pub fn show_all(self, yes: bool) -> Self {
+        self.show_nonprinting(yes).show_ends(yes).show_tabs(yes)
+    }
+
+    /// Collapse repeated blank lines into one (`-s`).
+    pub fn squeeze_blank(mut self, yes: bool) -> Self {
+        self.squeeze_blank = yes;
+        self
+    }
+
+    /// Restart numbering with every input instead of counting on.
+    pub fn per_file_numbering(mut self, yes: bool) -> Self {
+        self.per_file_numbering = yes;
+        self
+    }
+
+    // Whether any option asks to change the output
+    fn formatting(&self) -> bool {
+        self.number_lines
+            || self.number_nonblank_lines
+            || self.show_ends
+            || self.show_tabs
+            || self.show_nonprinting
+            || self.squeeze_blank
+    }
+}
+
+// What needs to be remembered from one line to the next
+#[derive(Debug)]
+struct LineState {
+    last_num: usize,
+    prev_blank: bool,
+    // False when the previous input ended without a newline,
+    // its last line then goes on in the next input
+    at_line_start: bool,
+}
+
+impl Default for LineState {
+    fn default() -> Self {
+        Self {
+            last_num: 0,
+            prev_blank: false,
+            at_line_start: true,
+        }
+    }
 }
 
-pub fn run(config: Config) -> MyResult<()> {
+// Returns Ok(false) when some file could not be opened.
+// The remaining files are still printed so the caller only has to
+// turn that into the exit status.
+pub fn run(config: Config) -> MyResult<bool> {
     // For quick and dirty debugging
     //dbg!(config);
-    for filename in config.files {
-        match open(&filename) {
-            Err(err) => eprintln!("Failed to open {}: {}", filename, err),
+    let mut stdout = io::stdout().lock();
+    let mut all_opened = true;
+    // Numbering carries on from one file to the next, like GNU cat
+    let mut state = LineState::default();
+    for filename in &config.files {
+        // Without formatting the input goes out byte for byte
+        if !config.formatting() {
+            all_opened &= copy_raw(filename, &mut stdout)?;
+            continue;
+        }
+        match open(filename) {
+            Err(err) => {
+                eprintln!("Failed to open {}: {}", filename, err);
+                all_opened = false;
+            }
             // There is stdin?
-            Ok(file) => {
-                let mut last_num = 0;
-                // Using lines() means we replace Windows CRLF
-                // with Unix-style newlines
-                for (line_num, line_result) in file.lines().enumerate() {
-                    let line = line_result?;
-                    // Print with line numbers if want
-                    if config.number_lines {
-                        println!("{:>6}\t{}", line_num + 1, line);
-                    } else if config.number_nonblank_lines {
-                        // Apply line numbers for non-blank lines
-                        if !line.is_empty() {
-                            last_num += 1;
-                            println!("{:>6}\t{}", last_num, line);
-                        } else {
-                            println!();
-                        }
-                    } else {
-                        println!("{}", line);
-                    }
-                }
+            Ok(file) => cat_input(file, &mut stdout, &config, &mut state)?,
+        }
+    }
+    Ok(all_opened)
+}
+
+/// Concatenate `inputs` into `out` using the numbering and display options
+/// of `config`. The files listed in `config` are not used.
+pub fn run_to<W: Write, R: BufRead>(
+    config: &Config,
+    inputs: impl IntoIterator<Item = R>,
+    out: &mut W,
+) -> MyResult<()> {
+    let mut state = LineState::default();
+    for input in inputs {
+        cat_input(input, out, config, &mut state)?;
+    }
+    Ok(())
+}
+
+// Print one input, carrying the numbering over from the previous one
+fn cat_input(
+    mut file: impl BufRead,
+    out: &mut impl Write,
+    config: &Config,
+    state: &mut LineState,
+) -> MyResult<()> {
+    if !config.formatting() {
+        io::copy(&mut file, out)?;
+        return Ok(());
+    }
+    if config.per_file_numbering {
+        *state = LineState::default();
+    }
+    cat_lines(file, out, config, state)
+}
+
+// Copy a file unchanged. Plain files and stdin are passed to io::copy
+// as they are, so on Linux it can hand the work to copy_file_range,
+// sendfile or splice instead of going through a userspace buffer.
+// Returns false when the file could not be opened.
+fn copy_raw(filename: &str, out: &mut io::StdoutLock) -> MyResult<bool> {
+    match filename {
+        "-" => {
+            io::copy(&mut io::stdin().lock(), out)?;
+        }
+        _ => match File::open(filename) {
+            Err(err) => {
+                eprintln!("Failed to open {}: {}", filename, err);
+                return Ok(false);
+            }
+            Ok(mut file) => {
+                io::copy(&mut file, out)?;
+            }
+        },
+    }
+    Ok(true)
+}
+
+// Copy the input line by line, applying numbering and display options.
+// Lines are handled as bytes so CRLF endings and invalid UTF-8 survive.
+fn cat_lines(
+    mut file: impl BufRead,
+    out: &mut impl Write,
+    config: &Config,
+    state: &mut LineState,
+) -> MyResult<()> {
+    let mut line = Vec::new();
+    let mut rendered = Vec::new();
+    loop {
+        line.clear();
+        if file.read_until(b'\n', &mut line)? == 0 {
+            break;
+        }
+        // The last line of a file may not have a newline
+        let (content, newline) = match line.strip_suffix(b"\n") {
+            Some(content) => (content, true),
+            None => (&line[..], false),
+        };
+        // The rest of a line started by the previous input is neither
+        // blank nor numbered
+        let continued = !state.at_line_start;
+        state.at_line_start = newline;
+        let blank = content.is_empty() && !continued;
+        if config.squeeze_blank && blank && state.prev_blank {
+            continue;
+        }
+        state.prev_blank = blank;
+
+        // Print with line numbers if want, -b skips blank lines
+        if !continued && (config.number_lines || (config.number_nonblank_lines && !blank)) {
+            state.last_num += 1;
+            write!(out, "{:>6}\t", state.last_num)?;
+        }
+        // With -E a CRLF ending shows up as ^M$, like GNU cat
+        let crlf = config.show_ends && newline && content.ends_with(b"\r");
+        let content = if crlf {
+            &content[..content.len() - 1]
+        } else {
+            content
+        };
+        rendered.clear();
+        for byte in content {
+            render_byte(*byte, config, &mut rendered);
+        }
+        if newline {
+            if crlf {
+                rendered.extend_from_slice(b"^M");
+            }
+            if config.show_ends {
+                rendered.push(b'$');
             }
+            rendered.push(b'\n');
         }
+        out.write_all(&rendered)?;
     }
     Ok(())
 }
 
+// Append a byte the way -T and -v want it displayed
+fn render_byte(byte: u8, config: &Config, rendered: &mut Vec<u8>) {
+    match byte {
+        b'\t' if config.show_tabs => rendered.extend_from_slice(b"^I"),
+        b'\t' => rendered.push(byte),
+        _ if !config.show_nonprinting => rendered.push(byte),
+        // High bytes are shown as M- followed by their 7-bit form
+        128.. => {
+            rendered.extend_from_slice(b"M-");
+            render_caret(byte - 128, rendered);
+        }
+        _ => render_caret(byte, rendered),
+    }
+}
+
+// Caret notation for control characters, ^@ through ^_ and ^?
+fn render_caret(byte: u8, rendered: &mut Vec<u8>) {
+    match byte {
+        0..=31 => rendered.extend_from_slice(&[b'^', byte + 64]),
+        127 => rendered.extend_from_slice(b"^?"),
+        _ => rendered.push(byte),
+    }
+}
+
 pub fn get_args() -> MyResult<Config> {
     let matches = App::new("catr")
         .version("0.1.0")
@@ -81,16 +327,58 @@ pub fn get_args() -> MyResult<Config> {
                 .help("Number of non-blank lines")
                 .takes_value(false),
         )
+        .arg(
+            Arg::with_name("per_file_numbering")
+                .long("per-file-numbering")
+                .help("Restart line numbers for each file"),
+        )
+        .arg(
+            Arg::with_name("show_all")
+                .short("A")
+                .long("show-all")
+                .help("Equivalent to -vET"),
+        )
+        .arg(
+            Arg::with_name("show_ends")
+                .short("E")
+                .long("show-ends")
+                .help("Display $ at end of each line"),
+        )
+        .arg(
+            Arg::with_name("show_tabs")
+                .short("T")
+                .long("show-tabs")
+                .help("Display TAB characters as ^I"),
+        )
+        .arg(
+            Arg::with_name("show_nonprinting")
+                .short("v")
+                .long("show-nonprinting")
+                .help("Use ^ and M- notation, except for LFD and TAB"),
+        )
+        .arg(
+            Arg::with_name("squeeze_blank")
+                .short("s")
+                .long("squeeze-blank")
+                .help("Suppress repeated empty output lines"),
+        )
         .get_matches();
 
+    // -A switches on all three display options at once
+    let show_all = matches.is_present("show_all");
+
     // Validate the arguments
-    Ok(Config {
+    Ok(Config::default()
         // Clap replaces invalid UTF-8 characters with \u{FFFD}
         // to ensure valid string
-        files: matches.values_of_lossy("files").unwrap(),
-        number_lines: matches.is_present("number"),
-        number_nonblank_lines: matches.is_present("number_nonblank"),
-    })
+        .files(matches.values_of_lossy("files").unwrap())
+        .number_lines(matches.is_present("number"))
+        .number_nonblank_lines(matches.is_present("number_nonblank"))
+        .show_ends(show_all || matches.is_present("show_ends"))
+        .show_tabs(show_all || matches.is_present("show_tabs"))
+        .show_nonprinting(show_all || matches.is_present("show_nonprinting"))
+        .squeeze_blank(matches.is_present("squeeze_blank"))
+        .per_file_numbering(matches.is_present("per_file_numbering")))
 }
 
 fn open(filename: &str) -> MyResult<Box<dyn BufRead>> {
@@ -1,8 +1,13 @@
 fn main() {
-    if let Err(e) = catr::get_args().and_then(catr::run) {
-        // Print from stderr
-        eprintln!("{}", e);
-        std::process::exit(1);
+    match catr::get_args().and_then(catr::run) {
+        Ok(true) => {}
+        // A file could not be opened, the error was already printed
+        Ok(false) => std::process::exit(1),
+        Err(e) => {
+            // Print from stderr
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
     }
     // Exit with code 0 by default, no need to specify
 }
@@ -0,0 +1,44 @@
+use anyhow::Result;
+use catr::{Config, run_to};
+use std::io::Cursor;
+
+// Run the library API over in-memory inputs and collect the output
+fn cat(config: &Config, inputs: &[&str]) -> Result<String> {
+    let mut out = Vec::new();
+    let inputs = inputs.iter().map(|input| Cursor::new(input.as_bytes()));
+    run_to(config, inputs, &mut out).map_err(|e| anyhow::anyhow!("{e}"))?;
+    Ok(String::from_utf8(out)?)
+}
+
+#[test]
+fn plain_concatenation() -> Result<()> {
+    let out = cat(&Config::default(), &["a\r\n", "b"])?;
+    assert_eq!(out, "a\r\nb");
+    Ok(())
+}
+
+#[test]
+fn numbering_continues_across_inputs() -> Result<()> {
+    let config = Config::default().number_lines(true);
+    let out = cat(&config, &["a\nb\n", "c\n"])?;
+    assert_eq!(out, "     1\ta\n     2\tb\n     3\tc\n");
+    Ok(())
+}
+
+#[test]
+fn per_file_numbering() -> Result<()> {
+    let config = Config::default()
+        .number_nonblank_lines(true)
+        .per_file_numbering(true);
+    let out = cat(&config, &["a\n\nb\n", "c\n"])?;
+    assert_eq!(out, "     1\ta\n\n     2\tb\n     1\tc\n");
+    Ok(())
+}
+
+#[test]
+fn show_all_and_squeeze() -> Result<()> {
+    let config = Config::default().show_all(true).squeeze_blank(true);
+    let out = cat(&config, &["a\tb\r\n\n\n\x01\n"])?;
+    assert_eq!(out, "a^Ib^M$\n$\n^A$\n");
+    Ok(())
+}
@@ -32,11 +32,30 @@ fn skip_bad_file() -> Result<()> {
     cargo::cargo_bin_cmd!("catr")
         .arg(&bad)
         .assert()
-        .success()
+        .failure()
         .stderr(predicate::str::is_match(expected)?);
     Ok(())
 }
 
+#[test]
+fn bad_file_still_prints_others() -> Result<()> {
+    let bad = gen_bad_file();
+    // Both the raw copy and the numbering paths go on after the error
+    cargo::cargo_bin_cmd!("catr")
+        .args([FOX, &bad, SPIDERS])
+        .assert()
+        .failure()
+        .stdout(predicate::str::starts_with("The quick brown fox"))
+        .stdout(predicate::str::ends_with("casually.\n"));
+    cargo::cargo_bin_cmd!("catr")
+        .args(["-n", FOX, &bad, SPIDERS])
+        .assert()
+        .failure()
+        .stdout(predicate::str::starts_with("     1\tThe quick brown fox"))
+        .stdout(predicate::str::ends_with("     4\tcasually.\n"));
+    Ok(())
+}
+
 #[test]
 fn bustle_stdin_b() -> Result<()> {
     run_stdin(
@@ -124,6 +143,22 @@ fn all_b() -> Result<()> {
     run(&[FOX, SPIDERS, BUSTLE, "-b"], "tests/expected/all.b.out")
 }
 
+#[test]
+fn all_n_per_file() -> Result<()> {
+    run(
+        &[FOX, SPIDERS, BUSTLE, "-n", "--per-file-numbering"],
+        "tests/expected/all.n.per-file.out",
+    )
+}
+
+#[test]
+fn all_b_per_file() -> Result<()> {
+    run(
+        &[FOX, SPIDERS, BUSTLE, "-b", "--per-file-numbering"],
+        "tests/expected/all.b.per-file.out",
+    )
+}
+
 // Simulate bad files
 fn gen_bad_file() -> String {
     loop {
@@ -171,3 +206,125 @@ fn run_stdin(input_file: &str, args: &[&str], expected_file: &str) -> Result<()>
 
     Ok(())
 }
+
+// Input exercising tabs, CRLF, blank runs, control and high bytes
+const SPECIAL: &[u8] = b"a\tb\r\n\n\n\nc\x01\x7f\xc3\xa9\n\nend";
+
+fn run_special(args: &[&str], expected: &[u8]) -> Result<()> {
+    cargo::cargo_bin_cmd!("catr")
+        .args(args)
+        .write_stdin(SPECIAL)
+        .assert()
+        .success()
+        .stdout(predicate::eq(expected));
+    Ok(())
+}
+
+#[test]
+fn show_ends() -> Result<()> {
+    run_special(&["-E"], b"a\tb^M$\n$\n$\n$\nc\x01\x7f\xc3\xa9$\n$\nend")
+}
+
+#[test]
+fn show_tabs() -> Result<()> {
+    run_special(&["-T"], b"a^Ib\r\n\n\n\nc\x01\x7f\xc3\xa9\n\nend")
+}
+
+#[test]
+fn show_nonprinting() -> Result<()> {
+    run_special(&["-v"], b"a\tb^M\n\n\n\nc^A^?M-CM-)\n\nend")
+}
+
+#[test]
+fn show_all() -> Result<()> {
+    for flag in ["-A", "--show-all"] {
+        run_special(&[flag], b"a^Ib^M$\n$\n$\n$\nc^A^?M-CM-)$\n$\nend")?;
+    }
+    Ok(())
+}
+
+#[test]
+fn squeeze_blank() -> Result<()> {
+    run_special(&["-s"], b"a\tb\r\n\nc\x01\x7f\xc3\xa9\n\nend")
+}
+
+#[test]
+fn squeeze_blank_number() -> Result<()> {
+    run_special(
+        &["-s", "-n"],
+        b"     1\ta\tb\r\n     2\t\n     3\tc\x01\x7f\xc3\xa9\n     4\t\n     5\tend",
+    )
+}
+
+#[test]
+fn number_nonblank_show_ends() -> Result<()> {
+    run_special(
+        &["-b", "-E"],
+        b"     1\ta\tb^M$\n$\n$\n$\n     2\tc\x01\x7f\xc3\xa9$\n$\n     3\tend",
+    )
+}
+
+#[test]
+fn passthrough_is_byte_exact() -> Result<()> {
+    // CRLF, invalid UTF-8 and no newline at the end all survive
+    let input: &[u8] = b"one\r\ntwo\xff\xfe\n\nlast";
+    cargo::cargo_bin_cmd!("catr")
+        .write_stdin(input)
+        .assert()
+        .success()
+        .stdout(predicate::eq(input));
+    Ok(())
+}
+
+#[test]
+fn concatenate_files_to_file() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    let first = dir.path().join("a");
+    let second = dir.path().join("b");
+    let output = dir.path().join("c");
+    fs::write(&first, b"a\r\n\xc3")?;
+    fs::write(&second, b"\xa9b")?;
+
+    let status = std::process::Command::new(env!("CARGO_BIN_EXE_catr"))
+        .arg(&first)
+        .arg(&second)
+        .stdout(fs::File::create(&output)?)
+        .status()?;
+    assert!(status.success());
+    assert_eq!(fs::read(&output)?, b"a\r\n\xc3\xa9b");
+    Ok(())
+}
+
+#[test]
+fn number_across_missing_newline() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    let first = dir.path().join("a");
+    let second = dir.path().join("b");
+    fs::write(&first, "one\n\nlasta")?;
+    fs::write(&second, "\n\nc\n")?;
+    let (first, second) = (first.to_str().unwrap(), second.to_str().unwrap());
+
+    // The first line of the second file finishes the last one of the first
+    let numbered = "     1\tone\n     2\t\n     3\tlasta\n     4\t\n     5\tc\n";
+    for (args, expected) in [
+        (vec!["-n"], numbered),
+        (vec!["-s", "-n"], numbered),
+        (vec!["-b"], "     1\tone\n\n     2\tlasta\n\n     3\tc\n"),
+        (
+            vec!["-b", "-E"],
+            "     1\tone$\n$\n     2\tlasta$\n$\n     3\tc$\n",
+        ),
+        (
+            vec!["-n", "--per-file-numbering"],
+            "     1\tone\n     2\t\n     3\tlasta     1\t\n     2\t\n     3\tc\n",
+        ),
+    ] {
+        cargo::cargo_bin_cmd!("catr")
+            .args(&args)
+            .args([first, second])
+            .assert()
+            .success()
+            .stdout(expected);
+    }
+    Ok(())
+}
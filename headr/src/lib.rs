@@ -1,67 +1,85 @@
+use crate::Count::*;
 use clap::{App, Arg};
+use std::collections::VecDeque;
 use std::error::Error;
-use std::fs::File;
+use std::fs::{self, File};
 use std::io::Read;
-use std::io::{self, BufRead, BufReader};
+use std::io::{self, BufRead, BufReader, Write};
 
 type MyResult<T> = Result<T, Box<dyn Error>>;
 
+// How many bytes to read at a time when holding back the end of the input
+const BLOCK_SIZE: usize = 64 * 1024;
+
+#[derive(Debug, PartialEq)]
+pub enum Count {
+    // Print the first N lines/bytes
+    First(usize),
+    // Print everything but the last N lines/bytes, like `-n -5`
+    AllBut(usize),
+}
+
+#[derive(Debug, PartialEq)]
+pub enum HeaderStyle {
+    // The classic `==> name <==` banner with blank lines in between
+    Banner,
+    // A user template with {name}, {path}, {size} and {index} placeholders
+    Template(String),
+    // The name followed by NUL, with NUL between files
+    Null,
+}
+
 #[derive(Debug)]
 pub struct Config {
     files: Vec<String>,
     // Pointer-sized unsigned integer type
     // varying from 4 bytes on 32-bit systems to 8 bytes on 64-bit systems
-    lines: usize,
-    bytes: Option<usize>,
+    lines: Count,
+    bytes: Option<Count>,
+    // Byte ending each line, `\n` unless changed with -z or --separator
+    separator: u8,
+    // Some(true) forces headers (-v), Some(false) hides them (-q)
+    headers: Option<bool>,
+    header_style: HeaderStyle,
 }
 
 pub fn run(config: Config) -> MyResult<()> {
     let num_files = config.files.len();
+    let show_headers = config.headers.unwrap_or(num_files > 1);
 
     for (file_num, filename) in config.files.iter().enumerate() {
         match open(filename) {
             Err(err) => eprintln!("{}: {}", filename, err),
             Ok(mut file) => {
                 // File separator
-                if num_files > 1 {
-                    println!(
-                        "{}==> {} <==",
-                        if file_num > 0 { "\n" } else { "" },
-                        filename,
-                    )
+                if show_headers {
+                    print_header(&config.header_style, filename, file_num);
                 }
 
-                if let Some(num_bytes) = config.bytes {
-                    // Read limited number of bytes from the buffer only
-                    // to properly size the buffer
-                    //let mut handle = file.take(num_bytes as u64);
-                    //let mut buffer = vec![0; num_bytes];
-                    // Pull some bytes from buffer to handle
-                    //let bytes_read = handle.read(&mut buffer)?;
-
-                    // Convert selected bytes to string
-                    // (some of them might not be a valid UTF-8)
-                    // so we use range operator ..
-                    // to select bytes actually read
-                    //print!("{}", String::from_utf8_lossy(&buffer[..bytes_read]))
-
-                    // Another approach, safe and shortest
-                    let bytes: Result<Vec<_>, _> = file.bytes().take(num_bytes).collect();
-                    print!("{}", String::from_utf8_lossy(&bytes?));
-                } else {
-                    // Read the whole content?
-                    let mut line = String::new();
-                    // Iterate counting up from 0 to requested number of lines
-                    for _ in 0..config.lines {
-                        // We use read_line() specifically
-                        // to also append the newline delimiter to the buffer
-                        let bytes = file.read_line(&mut line)?;
-                        if bytes == 0 {
-                            break;
+                match (&config.bytes, &config.lines) {
+                    (Some(AllBut(num_bytes)), _) => print_all_but_bytes(file, *num_bytes)?,
+                    (None, AllBut(num_lines)) => {
+                        print_all_but_lines(file, *num_lines, config.separator)?
+                    }
+                    (Some(First(num_bytes)), _) => {
+                        // Copy the bytes straight through so binary input stays intact
+                        io::copy(&mut file.take(*num_bytes as u64), &mut io::stdout().lock())?;
+                    }
+                    (None, First(num_lines)) => {
+                        let mut stdout = io::stdout().lock();
+                        let mut line = Vec::new();
+                        // Iterate counting up from 0 to requested number of lines
+                        for _ in 0..*num_lines {
+                            // We use read_until() specifically
+                            // to also append the separator to the buffer
+                            let bytes = file.read_until(config.separator, &mut line)?;
+                            if bytes == 0 {
+                                break;
+                            }
+                            stdout.write_all(&line)?;
+                            // Reset the buffer for the next read
+                            line.clear();
                         }
-                        print!("{}", line);
-                        // Reset the string for the next read from buffer
-                        line.clear();
                     }
                 }
             }
@@ -81,7 +99,8 @@ pub fn get_args() -> MyResult<Config> {
                 .short("n")
                 .long("lines")
                 .value_name("LINES")
-                .help("Number of lines")
+                .help("Number of lines, or all but the last LINES if negative")
+                .allow_hyphen_values(true)
                 .default_value("10"),
         )
         .arg(
@@ -90,8 +109,48 @@ pub fn get_args() -> MyResult<Config> {
                 .long("bytes")
                 .value_name("BYTES")
                 .takes_value(true)
+                .allow_hyphen_values(true)
                 .conflicts_with("lines")
-                .help("Number of bytes"),
+                .help("Number of bytes, or all but the last BYTES if negative"),
+        )
+        .arg(
+            Arg::with_name("zero_terminated")
+                .short("z")
+                .long("zero-terminated")
+                .help("Line separator is NUL, not newline"),
+        )
+        .arg(
+            Arg::with_name("separator")
+                .long("separator")
+                .value_name("BYTE")
+                .conflicts_with("zero_terminated")
+                .help("Line separator, a single byte or one of \\0, \\n, \\t"),
+        )
+        .arg(
+            Arg::with_name("quiet")
+                .short("q")
+                .long("quiet")
+                .overrides_with("verbose")
+                .help("Never print headers"),
+        )
+        .arg(
+            Arg::with_name("verbose")
+                .short("v")
+                .long("verbose")
+                .overrides_with("quiet")
+                .help("Always print headers"),
+        )
+        .arg(
+            Arg::with_name("header_format")
+                .long("header-format")
+                .value_name("TEMPLATE")
+                .help("Header template using {name}, {path}, {size} and {index}"),
+        )
+        .arg(
+            Arg::with_name("null_header")
+                .long("null-header")
+                .conflicts_with("header_format")
+                .help("Print each name followed by NUL and separate files with NUL"),
         )
         .arg(
             Arg::with_name("files")
@@ -106,7 +165,7 @@ pub fn get_args() -> MyResult<Config> {
         .value_of("lines")
         // First unpack &str from Some
         // then apply function for each line num?
-        .map(parse_positive_int)
+        .map(parse_count)
         // Conversion between Result<> and Option<> nested types
         // like Option<Result<>> to Result<Option<>>
         .transpose()
@@ -115,28 +174,201 @@ pub fn get_args() -> MyResult<Config> {
 
     let bytes = matches
         .value_of("bytes")
-        .map(parse_positive_int)
+        .map(parse_count)
         .transpose()
         .map_err(|e| format!("illegal byte count -- {}", e))?;
 
+    let separator = match matches.value_of("separator") {
+        Some(val) => parse_separator(val).map_err(|e| format!("illegal separator -- {}", e))?,
+        None if matches.is_present("zero_terminated") => b'\0',
+        None => b'\n',
+    };
+
     Ok(Config {
         files: matches.values_of_lossy("files").unwrap(),
         lines: lines.unwrap(),
         // Field init shorthand, suggested by Clippy,
         // just how they did it in Go? :)
         bytes,
+        separator,
+        headers: if matches.is_present("verbose") {
+            Some(true)
+        } else if matches.is_present("quiet") {
+            Some(false)
+        } else {
+            None
+        },
+        header_style: match matches.value_of("header_format") {
+            Some(template) => HeaderStyle::Template(template.to_string()),
+            None if matches.is_present("null_header") => HeaderStyle::Null,
+            None => HeaderStyle::Banner,
+        },
     })
 }
 
 fn parse_positive_int(val: &str) -> MyResult<usize> {
+    // An explicit plus sign is allowed, like GNU head
+    let unsigned = val.strip_prefix('+').unwrap_or(val);
+    // Split off a size suffix such as the "K" in "4K"
+    let split = unsigned
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(unsigned.len());
+    let (num, suffix) = unsigned.split_at(split);
     // Parse val as string to another type (specified by return type of the function)
-    match val.parse() {
-        Ok(n) if n > 0 => Ok(n),
+    match (num.parse::<usize>(), parse_suffix(suffix)) {
+        (Ok(n), Some(multiplier)) if n > 0 => n.checked_mul(multiplier).ok_or(From::from(val)),
         // Else conver string to Box<dyn Err>
         _ => Err(From::from(val)),
     }
 }
 
+// Multiplier for a size suffix, None when the suffix is unknown
+fn parse_suffix(suffix: &str) -> Option<usize> {
+    let multiplier = match suffix {
+        "" => 1,
+        "KB" | "kB" => 1000,
+        "K" | "k" | "KiB" => 1 << 10,
+        "MB" => 1000 * 1000,
+        "M" | "MiB" => 1 << 20,
+        "GB" => 1000 * 1000 * 1000,
+        "G" | "GiB" => 1 << 30,
+        _ => return None,
+    };
+    Some(multiplier)
+}
+
+fn print_header(style: &HeaderStyle, filename: &str, file_num: usize) {
+    match style {
+        HeaderStyle::Banner => println!(
+            "{}==> {} <==",
+            if file_num > 0 { "\n" } else { "" },
+            filename,
+        ),
+        HeaderStyle::Template(template) => {
+            // Stdin has neither a path nor a size to report,
+            // and neither does a file they cannot be found for
+            let (path, size) = match filename {
+                "-" => (None, None),
+                _ => (
+                    report(filename, fs::canonicalize(filename)),
+                    report(filename, fs::metadata(filename)).map(|meta| meta.len()),
+                ),
+            };
+            let path = path.map_or("-".to_string(), |path| path.display().to_string());
+            println!(
+                "{}",
+                format_header(template, filename, &path, size, file_num + 1)
+            );
+        }
+        HeaderStyle::Null => print!("{}{}\0", if file_num > 0 { "\0" } else { "" }, filename),
+    }
+}
+
+// Print an error the way run does for a file and carry on without the value
+fn report<T>(filename: &str, res: io::Result<T>) -> Option<T> {
+    res.map_err(|err| eprintln!("{}: {}", filename, err)).ok()
+}
+
+// Fill in the placeholders of a --header-format template.
+// The index counts files from 1.
+fn format_header(
+    template: &str,
+    name: &str,
+    path: &str,
+    size: Option<u64>,
+    index: usize,
+) -> String {
+    let size = size.map_or("-".to_string(), |size| size.to_string());
+    let index = index.to_string();
+    let mut header = String::new();
+    let mut rest = template;
+    // Substitute in a single pass so names containing braces are left alone
+    while let Some(start) = rest.find('{') {
+        header.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let (value, len) = if rest.starts_with("{name}") {
+            (name, "{name}".len())
+        } else if rest.starts_with("{path}") {
+            (path, "{path}".len())
+        } else if rest.starts_with("{size}") {
+            (size.as_str(), "{size}".len())
+        } else if rest.starts_with("{index}") {
+            (index.as_str(), "{index}".len())
+        } else {
+            ("{", 1)
+        };
+        header.push_str(value);
+        rest = &rest[len..];
+    }
+    header.push_str(rest);
+    header
+}
+
+// A separator is either a single byte or a common escape
+fn parse_separator(val: &str) -> MyResult<u8> {
+    match val.as_bytes() {
+        [byte] => Ok(*byte),
+        b"\\0" => Ok(b'\0'),
+        b"\\n" => Ok(b'\n'),
+        b"\\t" => Ok(b'\t'),
+        _ => Err(From::from(val)),
+    }
+}
+
+// A leading "-" asks for everything but the last N
+fn parse_count(val: &str) -> MyResult<Count> {
+    match val.strip_prefix('-') {
+        Some(num) => parse_positive_int(num)
+            .map(AllBut)
+            .map_err(|_| From::from(val)),
+        None => parse_positive_int(val).map(First),
+    }
+}
+
+// Print all but the last num_lines lines, only ever holding that many in memory
+fn print_all_but_lines(mut file: impl BufRead, num_lines: usize, separator: u8) -> MyResult<()> {
+    let mut stdout = io::stdout().lock();
+    let mut lookahead = VecDeque::new();
+    loop {
+        let mut line = Vec::new();
+        if file.read_until(separator, &mut line)? == 0 {
+            break;
+        }
+        lookahead.push_back(line);
+        // The oldest line can no longer be one of the last num_lines
+        if lookahead.len() > num_lines
+            && let Some(line) = lookahead.pop_front()
+        {
+            stdout.write_all(&line)?;
+        }
+    }
+    Ok(())
+}
+
+// Print all but the last num_bytes bytes, only ever holding that many in memory
+fn print_all_but_bytes(mut file: impl BufRead, num_bytes: usize) -> MyResult<()> {
+    let mut stdout = io::stdout().lock();
+    // A ring, so the bytes held back are not moved every time some go out
+    let mut lookahead = VecDeque::new();
+    let mut block = vec![0; BLOCK_SIZE];
+    loop {
+        let bytes_read = file.read(&mut block)?;
+        if bytes_read == 0 {
+            break;
+        }
+        lookahead.extend(&block[..bytes_read]);
+        if lookahead.len() > num_bytes {
+            let safe = lookahead.len() - num_bytes;
+            let (front, back) = lookahead.as_slices();
+            let from_front = safe.min(front.len());
+            stdout.write_all(&front[..from_front])?;
+            stdout.write_all(&back[..safe - from_front])?;
+            lookahead.drain(..safe);
+        }
+    }
+    Ok(())
+}
+
 fn open(filename: &str) -> MyResult<Box<dyn BufRead>> {
     match filename {
         // Input from stdin
@@ -162,4 +394,99 @@ fn test_parse_positive_int() {
     let res = parse_positive_int("0");
     assert!(res.is_err());
     assert_eq!(res.unwrap_err().to_string(), "0".to_string());
+
+    // A leading plus sign is accepted
+    assert_eq!(parse_positive_int("+2").unwrap(), 2);
+    assert_eq!(parse_positive_int("+4K").unwrap(), 4096);
+    assert!(parse_positive_int("++2").is_err());
+
+    // Size suffixes multiply the number
+    assert_eq!(parse_positive_int("4K").unwrap(), 4096);
+    assert_eq!(parse_positive_int("4KiB").unwrap(), 4096);
+    assert_eq!(parse_positive_int("4KB").unwrap(), 4000);
+    assert_eq!(parse_positive_int("2M").unwrap(), 2 * 1024 * 1024);
+    assert_eq!(parse_positive_int("1G").unwrap(), 1024 * 1024 * 1024);
+
+    // A suffix needs a number, a known unit and no overflow
+    let res = parse_positive_int("K");
+    assert!(res.is_err());
+    assert_eq!(res.unwrap_err().to_string(), "K".to_string());
+    let res = parse_positive_int("4X");
+    assert!(res.is_err());
+    assert_eq!(res.unwrap_err().to_string(), "4X".to_string());
+    let res = parse_positive_int("0K");
+    assert!(res.is_err());
+    assert!(parse_positive_int(&format!("{}G", usize::MAX)).is_err());
+}
+
+#[test]
+fn test_parse_count() {
+    // Plain numbers take the first N
+    let res = parse_count("3");
+    assert!(res.is_ok());
+    assert_eq!(res.unwrap(), First(3));
+
+    // Negative numbers take all but the last N
+    let res = parse_count("-3");
+    assert!(res.is_ok());
+    assert_eq!(res.unwrap(), AllBut(3));
+
+    // The error reports the whole value, sign included
+    let res = parse_count("-foo");
+    assert!(res.is_err());
+    assert_eq!(res.unwrap_err().to_string(), "-foo".to_string());
+
+    let res = parse_count("-0");
+    assert!(res.is_err());
+    assert_eq!(res.unwrap_err().to_string(), "-0".to_string());
+
+    let res = parse_count("--3");
+    assert!(res.is_err());
+    assert_eq!(res.unwrap_err().to_string(), "--3".to_string());
+}
+
+#[test]
+fn test_parse_separator() {
+    let res = parse_separator(",");
+    assert!(res.is_ok());
+    assert_eq!(res.unwrap(), b',');
+
+    // Escapes for bytes that are awkward to type
+    assert_eq!(parse_separator("\\0").unwrap(), b'\0');
+    assert_eq!(parse_separator("\\n").unwrap(), b'\n');
+    assert_eq!(parse_separator("\\t").unwrap(), b'\t');
+
+    // Anything longer than one byte is an error
+    let res = parse_separator("ab");
+    assert!(res.is_err());
+    assert_eq!(res.unwrap_err().to_string(), "ab".to_string());
+    assert!(parse_separator("").is_err());
+    assert!(parse_separator("é").is_err());
+}
+
+#[test]
+fn test_format_header() {
+    let res = format_header(
+        "# {index}: {name} ({size} bytes)",
+        "a.txt",
+        "/tmp/a.txt",
+        Some(42),
+        1,
+    );
+    assert_eq!(res, "# 1: a.txt (42 bytes)");
+
+    let res = format_header("{path}", "a.txt", "/tmp/a.txt", Some(42), 3);
+    assert_eq!(res, "/tmp/a.txt");
+
+    // Unknown sizes (stdin) are shown as a dash
+    let res = format_header("{name} {size}", "-", "-", None, 2);
+    assert_eq!(res, "- -");
+
+    // Text without placeholders is kept as is
+    let res = format_header("----", "a.txt", "/tmp/a.txt", Some(42), 1);
+    assert_eq!(res, "----");
+
+    // Unknown placeholders and placeholders inside names are not expanded
+    let res = format_header("{nope} {name}", "{size}", "/tmp/{size}", Some(42), 1);
+    assert_eq!(res, "{nope} {size}");
 }
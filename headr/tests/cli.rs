@@ -424,3 +424,299 @@ fn multiple_files_c4() -> Result<()> {
         "tests/expected/all.c4.out",
     )
 }
+
+// --------------------------------------------------
+// Everything but the last `num` lines of a file, computed independently
+fn all_but_lines(path: &str, num: usize) -> Result<Vec<u8>> {
+    let content = fs::read(path)?;
+    let lines: Vec<_> = content.split_inclusive(|b| *b == b'\n').collect();
+    Ok(lines[..lines.len().saturating_sub(num)].concat())
+}
+
+#[test]
+fn twelve_n_minus_2() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args(["-n", "-2", TWELVE])
+        .assert()
+        .success()
+        .stdout(predicate::eq(all_but_lines(TWELVE, 2)?));
+
+    Ok(())
+}
+
+#[test]
+fn twelve_n_minus_2_stdin() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args(["-n=-2"])
+        .write_stdin(fs::read(TWELVE)?)
+        .assert()
+        .success()
+        .stdout(predicate::eq(all_but_lines(TWELVE, 2)?));
+
+    Ok(())
+}
+
+#[test]
+fn three_n_minus_20() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args(["-n", "-20", THREE])
+        .assert()
+        .success()
+        .stdout("");
+
+    Ok(())
+}
+
+#[test]
+fn huge_n_minus_small_input() -> Result<()> {
+    // Nothing may be allocated up front for the lookahead
+    for count in ["-n", "-c"] {
+        Command::cargo_bin(PRG)?
+            .args([count, "-99999999999"])
+            .write_stdin("a\n")
+            .assert()
+            .success()
+            .stdout("");
+    }
+
+    Ok(())
+}
+
+#[test]
+fn c_minus_large_lookahead() -> Result<()> {
+    // Many blocks are held back at once, the ring wraps around while they go out
+    let content: Vec<u8> = (0..1_000_000u32).map(|n| (n % 251) as u8).collect();
+    let started = std::time::Instant::now();
+    Command::cargo_bin(PRG)?
+        .args(["-c", "-300000"])
+        .write_stdin(content.clone())
+        .assert()
+        .success()
+        .stdout(predicate::eq(&content[..700_000]));
+    assert!(started.elapsed() < std::time::Duration::from_secs(10));
+
+    Ok(())
+}
+
+#[test]
+fn two_c_minus_3() -> Result<()> {
+    let content = fs::read(TWO)?;
+    Command::cargo_bin(PRG)?
+        .args(["-c", "-3", TWO])
+        .assert()
+        .success()
+        .stdout(predicate::eq(&content[..content.len() - 3]));
+
+    Ok(())
+}
+
+#[test]
+fn two_c_minus_3_stdin() -> Result<()> {
+    let content = fs::read(TWO)?;
+    Command::cargo_bin(PRG)?
+        .args(["-c", "-3"])
+        .write_stdin(content.clone())
+        .assert()
+        .success()
+        .stdout(predicate::eq(&content[..content.len() - 3]));
+
+    Ok(())
+}
+
+#[test]
+fn large_stdin_c_minus() -> Result<()> {
+    // Bigger than a single read so the lookahead has to slide
+    let content: Vec<u8> = (0..200_000).map(|i| (i % 251) as u8).collect();
+    Command::cargo_bin(PRG)?
+        .args(["-c", "-70000"])
+        .write_stdin(content.clone())
+        .assert()
+        .success()
+        .stdout(predicate::eq(&content[..130_000]));
+
+    Ok(())
+}
+
+#[test]
+fn dies_bad_negative_lines() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args(["-n", "-foo", EMPTY])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("illegal line count -- -foo"));
+
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn bytes_are_not_lossy() -> Result<()> {
+    let content: Vec<u8> = (0..=255).rev().collect();
+    Command::cargo_bin(PRG)?
+        .args(["-c", "100"])
+        .write_stdin(content.clone())
+        .assert()
+        .success()
+        .stdout(predicate::eq(&content[..100]));
+
+    Ok(())
+}
+
+#[test]
+fn bytes_size_suffix() -> Result<()> {
+    let content: Vec<u8> = (0..10_000).map(|i| (i % 256) as u8).collect();
+    Command::cargo_bin(PRG)?
+        .args(["-c", "4K"])
+        .write_stdin(content.clone())
+        .assert()
+        .success()
+        .stdout(predicate::eq(&content[..4096]));
+    Command::cargo_bin(PRG)?
+        .args(["-c", "2KB"])
+        .write_stdin(content.clone())
+        .assert()
+        .success()
+        .stdout(predicate::eq(&content[..2000]));
+
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn zero_terminated() -> Result<()> {
+    for flag in ["-z", "--zero-terminated"] {
+        Command::cargo_bin(PRG)?
+            .args([flag, "-n", "2"])
+            .write_stdin("a\nb\0c\0d\0")
+            .assert()
+            .success()
+            .stdout("a\nb\0c\0");
+        Command::cargo_bin(PRG)?
+            .args([flag, "-n", "-1"])
+            .write_stdin("a\nb\0c\0d\0")
+            .assert()
+            .success()
+            .stdout("a\nb\0c\0");
+    }
+
+    Ok(())
+}
+
+#[test]
+fn custom_separator() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args(["--separator", ",", "-n", "2"])
+        .write_stdin("a,b,c\nd,e")
+        .assert()
+        .success()
+        .stdout("a,b,");
+    Command::cargo_bin(PRG)?
+        .args(["--separator", "\\0", "-n", "1"])
+        .write_stdin("a\0b\0")
+        .assert()
+        .success()
+        .stdout("a\0");
+
+    Ok(())
+}
+
+#[test]
+fn dies_bad_separator() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args(["--separator", "ab", EMPTY])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("illegal separator -- ab"));
+
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn quiet_hides_headers() -> Result<()> {
+    let expected = format!("{}{}", fs::read_to_string(ONE)?, fs::read_to_string(TWO)?);
+    for flag in ["-q", "--quiet"] {
+        Command::cargo_bin(PRG)?
+            .args([flag, ONE, TWO])
+            .assert()
+            .success()
+            .stdout(expected.clone());
+    }
+
+    Ok(())
+}
+
+#[test]
+fn verbose_single_file() -> Result<()> {
+    let expected = format!("==> {ONE} <==\n{}", fs::read_to_string(ONE)?);
+    for flag in ["-v", "--verbose"] {
+        Command::cargo_bin(PRG)?
+            .args([flag, ONE])
+            .assert()
+            .success()
+            .stdout(expected.clone());
+    }
+
+    Ok(())
+}
+
+#[test]
+fn header_format() -> Result<()> {
+    let expected = format!(
+        "# 1 {ONE} {} {}\n{}# 2 {TWO} {} {}\n{}",
+        fs::metadata(ONE)?.len(),
+        fs::canonicalize(ONE)?.display(),
+        fs::read_to_string(ONE)?,
+        fs::metadata(TWO)?.len(),
+        fs::canonicalize(TWO)?.display(),
+        fs::read_to_string(TWO)?
+    );
+    Command::cargo_bin(PRG)?
+        .args([
+            "--header-format",
+            "# {index} {name} {size} {path}",
+            ONE,
+            TWO,
+        ])
+        .assert()
+        .success()
+        .stdout(expected);
+
+    Ok(())
+}
+
+#[test]
+fn header_format_unknown_path() -> Result<()> {
+    // A pipe opens fine through /dev/stdin but has no real path,
+    // the files after it are still printed
+    let expected = format!(
+        "- 0\nx\n{} {}\n{}",
+        fs::canonicalize(TWO)?.display(),
+        fs::metadata(TWO)?.len(),
+        fs::read_to_string(TWO)?
+    );
+    Command::cargo_bin(PRG)?
+        .args(["--header-format", "{path} {size}", "/dev/stdin", TWO])
+        .write_stdin("x\n")
+        .assert()
+        .stdout(expected)
+        .stderr(predicate::str::starts_with("/dev/stdin: "));
+
+    Ok(())
+}
+
+#[test]
+fn null_header() -> Result<()> {
+    let expected = format!(
+        "{ONE}\0{}\0{TWO}\0{}",
+        fs::read_to_string(ONE)?,
+        fs::read_to_string(TWO)?
+    );
+    Command::cargo_bin(PRG)?
+        .args(["--null-header", ONE, TWO])
+        .assert()
+        .success()
+        .stdout(expected);
+
+    Ok(())
+}
@@ -0,0 +1,218 @@
+use crate::prefix::PrefixWriter;
+use crate::{MyResult, print_header};
+use std::fs::{self, File};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::os::unix::fs::MetadataExt;
+use std::thread;
+use std::time::Duration;
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum FollowMode {
+    // Keep reading from the open file handle, like `tail -f`
+    Descriptor,
+    // Reopen the file when the name points somewhere else, like `tail -F`
+    Name,
+}
+
+// A file being followed after the initial snapshot was printed
+#[derive(Debug)]
+pub struct Followed {
+    name: String,
+    // Position among the inputs on the command line
+    input_num: usize,
+    file: Option<File>,
+    // Offset of the next byte we have not printed yet
+    pos: u64,
+    // Put in front of every line with `--prefix`
+    label: Option<Vec<u8>>,
+    // A partial line waiting for its delimiter, only used with a label
+    pending: Vec<u8>,
+}
+
+impl Followed {
+    // Start following an open file right after the snapshot. The snapshot
+    // shares the file offset, so that is where it stopped reading, and
+    // anything appended since then is still ahead. A snapshot that read
+    // nothing leaves the offset alone, then `size` from before it is used.
+    pub fn new(
+        name: &str,
+        input_num: usize,
+        mut file: File,
+        size: u64,
+        label: Option<Vec<u8>>,
+    ) -> MyResult<Self> {
+        let pos = file.stream_position()?.max(size);
+        Ok(Self {
+            name: name.to_string(),
+            input_num,
+            file: Some(file),
+            pos,
+            label,
+            pending: Vec::new(),
+        })
+    }
+
+    // A file that could not be opened yet, only useful with `-F`
+    pub fn missing(name: &str, input_num: usize, label: Option<Vec<u8>>) -> Self {
+        Self {
+            name: name.to_string(),
+            input_num,
+            file: None,
+            pos: 0,
+            label,
+            pending: Vec::new(),
+        }
+    }
+
+    // Print the new data, holding back a trailing partial line when
+    // prefixing so lines from different files never get mixed up.
+    // With `flush` the partial line is printed too.
+    fn print(&mut self, buf: &[u8], delimiter: u8, flush: bool) -> MyResult<()> {
+        let mut stdout = io::stdout().lock();
+        match &self.label {
+            None => stdout.write_all(buf)?,
+            Some(label) => {
+                self.pending.extend_from_slice(buf);
+                let end = if flush {
+                    self.pending.len()
+                } else {
+                    self.pending
+                        .iter()
+                        .rposition(|byte| *byte == delimiter)
+                        .map_or(0, |pos| pos + 1)
+                };
+                let mut out = PrefixWriter::new(&mut stdout, Some(label), delimiter);
+                out.write_all(&self.pending[..end])?;
+                out.finish()?;
+                self.pending.drain(..end);
+            }
+        }
+        stdout.flush()?;
+        Ok(())
+    }
+
+    // Read everything appended since the last poll into buf
+    fn read_new(&mut self, buf: &mut Vec<u8>) -> MyResult<usize> {
+        let Some(file) = self.file.as_mut() else {
+            return Ok(0);
+        };
+        if file.metadata()?.len() < self.pos {
+            eprintln!("tailr: {}: file truncated", self.name);
+            self.pos = 0;
+        }
+        file.seek(SeekFrom::Start(self.pos))?;
+        let bytes_read = file.read_to_end(buf)?;
+        self.pos += bytes_read as u64;
+        Ok(bytes_read)
+    }
+
+    // Check whether the name still refers to the file we hold open
+    fn check_rotation(&self) -> Rotation {
+        match (fs::metadata(&self.name), &self.file) {
+            (Err(_), Some(_)) => {
+                eprintln!("tailr: '{}' has become inaccessible", self.name);
+                Rotation::Gone
+            }
+            (Ok(meta), Some(file)) => match file.metadata() {
+                Ok(current) if meta.dev() == current.dev() && meta.ino() == current.ino() => {
+                    Rotation::Unchanged
+                }
+                _ => match File::open(&self.name) {
+                    Ok(file) => {
+                        eprintln!(
+                            "tailr: '{}' has been replaced;  following new file",
+                            self.name
+                        );
+                        Rotation::Replaced(file)
+                    }
+                    Err(_) => Rotation::Unchanged,
+                },
+            },
+            (Ok(_), None) => match File::open(&self.name) {
+                Ok(file) => {
+                    eprintln!("tailr: '{}' has appeared;  following new file", self.name);
+                    Rotation::Replaced(file)
+                }
+                Err(_) => Rotation::Unchanged,
+            },
+            (Err(_), None) => Rotation::Unchanged,
+        }
+    }
+}
+
+// What happened to a followed name since the last poll
+enum Rotation {
+    Unchanged,
+    // The name was removed or renamed away
+    Gone,
+    // The name now points to a different (or a newly created) file
+    Replaced(File),
+}
+
+// Check whether a process is still running without sending it a signal
+fn process_alive(pid: i32) -> bool {
+    // SAFETY: signal 0 only performs the existence and permission checks
+    let res = unsafe { libc::kill(pid, 0) };
+    // EPERM means the process exists but belongs to someone else
+    res == 0 || io::Error::last_os_error().raw_os_error() == Some(libc::EPERM)
+}
+
+#[derive(Debug)]
+pub struct FollowOptions {
+    pub mode: FollowMode,
+    // Print `==> name <==` whenever the active file changes
+    pub headers: bool,
+    // How long to wait between two polls
+    pub interval: Duration,
+    // Stop once this process has exited
+    pub pid: Option<i32>,
+    pub delimiter: u8,
+}
+
+// Keep printing data appended to the files until the process is killed,
+// or until `pid` exits when one is given.
+// `last` is the position among all inputs of the one printed most recently,
+// stdin included, so that a header is only printed when the active file changes.
+pub fn follow_files(
+    mut files: Vec<Followed>,
+    mut last: Option<usize>,
+    options: &FollowOptions,
+) -> MyResult<()> {
+    let mut buf = Vec::new();
+    loop {
+        // Checked before reading so whatever the process wrote last still gets drained
+        let alive = options.pid.is_none_or(process_alive);
+        for followed in &mut files {
+            let rotation = if options.mode == FollowMode::Name {
+                followed.check_rotation()
+            } else {
+                Rotation::Unchanged
+            };
+
+            // Drain what is left in the old file before switching over
+            followed.read_new(&mut buf)?;
+            match rotation {
+                Rotation::Unchanged => {}
+                Rotation::Gone => followed.file = None,
+                Rotation::Replaced(file) => {
+                    followed.file = Some(file);
+                    followed.pos = 0;
+                    followed.read_new(&mut buf)?;
+                }
+            }
+
+            if !buf.is_empty() || (!alive && !followed.pending.is_empty()) {
+                if options.headers && last != Some(followed.input_num) {
+                    print_header(&followed.name, last.is_some());
+                }
+                last = Some(followed.input_num);
+                followed.print(&buf, options.delimiter, !alive)?;
+                buf.clear();
+            }
+        }
+        if !alive {
+            return Ok(());
+        }
+        thread::sleep(options.interval);
+    }
+}
@@ -1,22 +1,35 @@
+mod follow;
+mod prefix;
+
 use crate::TakeValue::*;
 use clap::{App, Arg};
+use follow::{FollowMode, FollowOptions, Followed, follow_files};
 /// Create lazily evaluated statics (created when 1st use)
 use once_cell::sync::OnceCell;
+use prefix::PrefixWriter;
 use regex::Regex;
+use std::collections::VecDeque;
 use std::error::Error;
 use std::fs::File;
 use std::io::{
+    self,
     BufRead,
     BufReader,
     Read, // Read bytes from a source
     Seek, // A cursor which can be moved within a stream of bytes to track bytes?
     SeekFrom,
+    Write,
 };
+use std::mem;
+use std::time::Duration;
 
 type MyResult<T> = Result<T, Box<dyn Error>>;
 
 static NUM_RE: OnceCell<Regex> = OnceCell::new();
 
+// How many bytes to read at a time when scanning a file backwards
+const BLOCK_SIZE: usize = 64 * 1024;
+
 #[derive(Debug, PartialEq)]
 enum TakeValue {
     // +0 means everything should be selected
@@ -25,12 +38,31 @@ enum TakeValue {
     TakeNum(i64),
 }
 
+// Regular files can be seeked, anything else (stdin, pipes) is read once
+enum Input {
+    File(File),
+    Stream(Box<dyn BufRead>),
+}
+
 #[derive(Debug)]
 struct Config {
     files: Vec<String>,
     lines: TakeValue,
     bytes: Option<TakeValue>,
     quiet: bool,
+    verbose: bool,
+    // Start byte output on a UTF-8 character boundary
+    chars: bool,
+    // Byte that terminates a line, `\n` or NUL with `-z`
+    delimiter: u8,
+    follow: Option<FollowMode>,
+    // How long to wait between polls in follow mode
+    sleep_interval: Duration,
+    // Stop following once this process has exited
+    pid: Option<i32>,
+    // Label every line with its file name instead of printing headers
+    prefix: bool,
+    color: bool,
 }
 
 fn main() {
@@ -49,8 +81,8 @@ fn get_args() -> MyResult<Config> {
             Arg::with_name("files")
                 .value_name("FILE")
                 .help("Input file(s)")
-                .required(true)
-                .multiple(true),
+                .multiple(true)
+                .default_value("-"),
         )
         .arg(
             Arg::with_name("lines")
@@ -68,11 +100,67 @@ fn get_args() -> MyResult<Config> {
                 .conflicts_with("lines")
                 .help("Number of bytes"),
         )
+        .arg(
+            Arg::with_name("chars")
+                .long("chars")
+                .requires("bytes")
+                .help("Align the start of -c output to a UTF-8 character"),
+        )
         .arg(
             Arg::with_name("quiet")
                 .short("q")
                 .long("quiet")
-                .help("Suppress headers"),
+                .help("Suppress headers")
+                .overrides_with("verbose"),
+        )
+        .arg(
+            Arg::with_name("verbose")
+                .short("v")
+                .long("verbose")
+                .help("Always print headers")
+                .overrides_with("quiet"),
+        )
+        .arg(
+            Arg::with_name("zero_terminated")
+                .short("z")
+                .long("zero-terminated")
+                .help("Line delimiter is NUL, not newline"),
+        )
+        .arg(
+            Arg::with_name("follow")
+                .short("f")
+                .long("follow")
+                .help("Output appended data as the file grows"),
+        )
+        .arg(
+            Arg::with_name("follow_name")
+                .short("F")
+                .help("Follow the file by name, reopening it when rotated or truncated"),
+        )
+        .arg(
+            Arg::with_name("prefix")
+                .long("prefix")
+                .help("Prefix each line with the name of its file"),
+        )
+        .arg(
+            Arg::with_name("color")
+                .long("color")
+                .requires("prefix")
+                .help("Color the prefixes, one color per file"),
+        )
+        .arg(
+            Arg::with_name("sleep_interval")
+                .short("s")
+                .long("sleep-interval")
+                .value_name("SECS")
+                .help("Seconds to sleep between polls when following")
+                .default_value("1"),
+        )
+        .arg(
+            Arg::with_name("pid")
+                .long("pid")
+                .value_name("PID")
+                .help("With -f, exit after process PID dies"),
         )
         .get_matches();
 
@@ -87,54 +175,190 @@ fn get_args() -> MyResult<Config> {
         .transpose()
         .map_err(|e| format!("illegal byte count -- {}", e))?;
 
+    let sleep_interval = matches
+        .value_of("sleep_interval")
+        .map(parse_interval)
+        .transpose()
+        .map_err(|e| format!("invalid number of seconds -- {}", e))?;
+    let pid = matches
+        .value_of("pid")
+        .map(|val| match val.parse() {
+            Ok(pid) if pid > 0 => Ok(pid),
+            _ => Err(val),
+        })
+        .transpose()
+        .map_err(|e| format!("invalid PID -- {}", e))?;
+
     Ok(Config {
         files: matches.values_of_lossy("files").unwrap(),
         lines: lines.unwrap(),
         bytes,
         quiet: matches.is_present("quiet"),
+        verbose: matches.is_present("verbose"),
+        chars: matches.is_present("chars"),
+        delimiter: if matches.is_present("zero_terminated") {
+            b'\0'
+        } else {
+            b'\n'
+        },
+        follow: if matches.is_present("follow_name") {
+            Some(FollowMode::Name)
+        } else if matches.is_present("follow") {
+            Some(FollowMode::Descriptor)
+        } else {
+            None
+        },
+        sleep_interval: sleep_interval.unwrap(),
+        pid,
+        prefix: matches.is_present("prefix"),
+        color: matches.is_present("color"),
     })
 }
 
 fn run(config: Config) -> MyResult<()> {
     let num_files = config.files.len();
+    // Prefixed lines already say where they come from
+    let headers = !config.prefix && (config.verbose || (!config.quiet && num_files > 1));
+    let mut followed = Vec::new();
+    let mut last = None;
+    let mut missing = false;
     // Iterator yields the value and its index wow
     for (file_num, filename) in config.files.iter().enumerate() {
-        match File::open(filename) {
-            Err(err) => eprintln!("{}: {}", filename, err),
-            Ok(file) => {
-                if !config.quiet && num_files > 1 {
-                    println!(
-                        "{}==> {} <==",
-                        if file_num > 0 { "\n" } else { "" },
-                        filename
-                    );
+        let label = config
+            .prefix
+            .then(|| prefix::label(display_name(filename), file_num, config.color));
+        let stdout = io::stdout().lock();
+        let mut out = PrefixWriter::new(stdout, label.as_deref(), config.delimiter);
+        match open(filename) {
+            Err(err) => {
+                eprintln!("{}: {}", filename, err);
+                missing = true;
+                // Following by name keeps retrying until the file shows up
+                if config.follow == Some(FollowMode::Name) {
+                    followed.push(Followed::missing(filename, file_num, label.clone()));
+                }
+            }
+            Ok(Input::Stream(reader)) => {
+                if headers {
+                    print_header(display_name(filename), file_num > 0);
                 }
-                let (total_lines, total_bytes) = count_lines_bytes(filename)?;
-                let file = BufReader::new(file);
                 if let Some(num_bytes) = &config.bytes {
-                    print_bytes(file, num_bytes, total_bytes)?;
+                    print_stream_bytes(reader, &mut out, num_bytes, config.chars)?;
                 } else {
-                    print_lines(file, &config.lines, total_lines)?;
+                    print_stream_lines(reader, &mut out, &config.lines, config.delimiter)?;
                 }
+                // Not followed, but whatever is appended to a file next needs its header again
+                last = Some(file_num);
             }
+            Ok(Input::File(file)) => {
+                if headers {
+                    print_header(filename, file_num > 0);
+                }
+                let size = file.metadata()?.len();
+                let reader = BufReader::new(file.try_clone()?);
+                if let Some(num_bytes) = &config.bytes {
+                    // The size is all we need to know to seek for bytes
+                    print_bytes(reader, &mut out, num_bytes, size as i64, config.chars)?;
+                } else if let TakeNum(num) = config.lines
+                    && num <= 0
+                {
+                    print_last_lines(reader, &mut out, num.unsigned_abs(), config.delimiter)?;
+                } else {
+                    // Only +N offsets need to know how many lines there are
+                    let (total_lines, _) = count_lines_bytes(filename, config.delimiter)?;
+                    print_lines(
+                        reader,
+                        &mut out,
+                        &config.lines,
+                        total_lines,
+                        config.delimiter,
+                    )?;
+                }
+                last = Some(file_num);
+                if config.follow.is_some() {
+                    followed.push(Followed::new(
+                        filename,
+                        file_num,
+                        file,
+                        size,
+                        label.clone(),
+                    )?);
+                }
+            }
+        }
+        out.finish()?;
+    }
+    if let Some(mode) = config.follow {
+        // Pipes are not followed and without -F neither are files that failed to open,
+        // so there may be nothing left to wait for
+        if followed.is_empty() {
+            if missing {
+                return Err(From::from("tailr: no files remaining"));
+            }
+            return Ok(());
         }
+        let options = FollowOptions {
+            mode,
+            headers,
+            interval: config.sleep_interval,
+            pid: config.pid,
+            delimiter: config.delimiter,
+        };
+        follow_files(followed, last, &options)?;
     }
     Ok(())
 }
 
+// Print the `==> name <==` banner that separates the output of each file
+fn print_header(filename: &str, leading_newline: bool) {
+    println!(
+        "{}==> {} <==",
+        if leading_newline { "\n" } else { "" },
+        filename
+    );
+}
+
+fn open(filename: &str) -> MyResult<Input> {
+    match filename {
+        "-" => Ok(Input::Stream(Box::new(BufReader::new(io::stdin())))),
+        _ => {
+            let file = File::open(filename)?;
+            // Pipes and devices cannot seek, so treat them like stdin
+            if file.metadata()?.is_file() {
+                Ok(Input::File(file))
+            } else {
+                Ok(Input::Stream(Box::new(BufReader::new(file))))
+            }
+        }
+    }
+}
+
+// Name shown in headers, the way GNU tail calls stdin
+fn display_name(filename: &str) -> &str {
+    if filename == "-" {
+        "standard input"
+    } else {
+        filename
+    }
+}
+
 fn parse_num(val: &str) -> MyResult<TakeValue> {
-    // Optionally capturing preceding group (+ or -)
-    let num_re = NUM_RE.get_or_init(|| Regex::new(r"^([+-])?(\d+)$").unwrap());
+    // Optionally capturing preceding group (+ or -) and a size suffix
+    let num_re = NUM_RE.get_or_init(|| Regex::new(r"^([+-])?(\d+)([[:alpha:]]*)$").unwrap());
 
     match num_re.captures(val) {
         Some(caps) => {
             // Return the matched preceding group (either + or -)
             let sign = caps.get(1).map_or("-", |m| m.as_str());
             let num = format!("{}{}", sign, caps.get(2).unwrap().as_str());
+            let multiplier = parse_suffix(caps.get(3).unwrap().as_str());
 
             // Ok(val) is a pattern, and we do pattern matching here.
             // Continue execution if parsing succeeds
-            if let Ok(val) = num.parse() {
+            if let Some(val) = multiplier
+                .zip(num.parse::<i64>().ok())
+                .and_then(|(multiplier, num)| num.checked_mul(multiplier))
+            {
                 if sign == "+" && val == 0 {
                     Ok(PlusZero)
                 } else {
@@ -148,9 +372,35 @@ fn parse_num(val: &str) -> MyResult<TakeValue> {
     }
 }
 
+// Parse a number of seconds such as "1" or "0.5"
+fn parse_interval(val: &str) -> MyResult<Duration> {
+    match val.parse::<f64>() {
+        Ok(secs) if secs.is_finite() && secs >= 0.0 => Ok(Duration::from_secs_f64(secs)),
+        _ => Err(From::from(val)),
+    }
+}
+
+// Multiplier for a GNU size suffix, None when the suffix is unknown
+fn parse_suffix(suffix: &str) -> Option<i64> {
+    let multiplier = match suffix {
+        "" => 1,
+        "b" => 512,
+        "KB" | "kB" => 1000,
+        "K" | "k" | "KiB" => 1 << 10,
+        "MB" => 1000 * 1000,
+        "M" | "MiB" => 1 << 20,
+        "GB" => 1000 * 1000 * 1000,
+        "G" | "GiB" => 1 << 30,
+        "TB" => 1000 * 1000 * 1000 * 1000,
+        "T" | "TiB" => 1 << 40,
+        _ => return None,
+    };
+    Some(multiplier)
+}
+
 // Read a file from a given byte or line location
 // and return the total number of lines and bytes
-fn count_lines_bytes(filename: &str) -> MyResult<(i64, i64)> {
+fn count_lines_bytes(filename: &str, delimiter: u8) -> MyResult<(i64, i64)> {
     // Check if user requests more lines or bytes than the file contains
     let mut file = BufReader::new(File::open(filename)?);
     let mut num_lines = 0;
@@ -159,7 +409,7 @@ fn count_lines_bytes(filename: &str) -> MyResult<(i64, i64)> {
     let mut buf = Vec::new();
     loop {
         // Read into buf until break line delimiter
-        let bytes_read = file.read_until(b'\n', &mut buf)?;
+        let bytes_read = file.read_until(delimiter, &mut buf)?;
         if bytes_read == 0 {
             // Reach EOF?
             break;
@@ -171,18 +421,24 @@ fn count_lines_bytes(filename: &str) -> MyResult<(i64, i64)> {
     Ok((num_lines, num_bytes))
 }
 
-fn print_lines(mut file: impl BufRead, num_lines: &TakeValue, total_lines: i64) -> MyResult<()> {
+fn print_lines(
+    mut file: impl BufRead,
+    out: &mut impl Write,
+    num_lines: &TakeValue,
+    total_lines: i64,
+    delimiter: u8,
+) -> MyResult<()> {
     // We can find the starting line's index using num_lines and total_lines?
     if let Some(start) = get_start_index(num_lines, total_lines) {
         let mut line_num = 0;
         let mut buf = Vec::new();
         loop {
-            let bytes_read = file.read_until(b'\n', &mut buf)?;
+            let bytes_read = file.read_until(delimiter, &mut buf)?;
             if bytes_read == 0 {
                 break;
             }
             if line_num >= start {
-                print!("{}", String::from_utf8_lossy(&buf));
+                out.write_all(&buf)?;
             }
             line_num += 1;
             buf.clear()
@@ -191,26 +447,180 @@ fn print_lines(mut file: impl BufRead, num_lines: &TakeValue, total_lines: i64)
     Ok(())
 }
 
-fn print_bytes<T: Read + Seek>(
+// Print the last num_lines lines without reading the whole file
+fn print_last_lines<T: BufRead + Seek>(
+    mut file: T,
+    out: &mut impl Write,
+    num_lines: u64,
+    delimiter: u8,
+) -> MyResult<()> {
+    if num_lines == 0 {
+        return Ok(());
+    }
+    let start = find_tail_start(&mut file, num_lines, delimiter)?;
+    file.seek(SeekFrom::Start(start))?;
+    io::copy(&mut file, out)?;
+    Ok(())
+}
+
+// Scan backwards from the end in blocks and return the byte offset
+// where the last num_lines lines start
+fn find_tail_start<T: Read + Seek>(file: &mut T, num_lines: u64, delimiter: u8) -> MyResult<u64> {
+    let size = file.seek(SeekFrom::End(0))?;
+    let mut block = vec![0; BLOCK_SIZE];
+    let mut end = size;
+    let mut newlines = 0;
+    while end > 0 {
+        let start = end.saturating_sub(BLOCK_SIZE as u64);
+        let chunk = &mut block[..(end - start) as usize];
+        file.seek(SeekFrom::Start(start))?;
+        file.read_exact(chunk)?;
+        for (i, byte) in chunk.iter().enumerate().rev() {
+            let pos = start + i as u64;
+            // The delimiter ending the last line does not start a new line
+            if *byte == delimiter && pos + 1 != size {
+                newlines += 1;
+                if newlines == num_lines {
+                    return Ok(pos + 1);
+                }
+            }
+        }
+        end = start;
+    }
+    // Fewer lines than requested, so take the whole file
+    Ok(0)
+}
+
+// Print lines from an input that can only be read once.
+// Only the last lines are kept around, so memory is bounded by the count.
+fn print_stream_lines(
+    mut file: impl BufRead,
+    out: &mut impl Write,
+    num_lines: &TakeValue,
+    delimiter: u8,
+) -> MyResult<()> {
+    let mut line = Vec::new();
+    match num_lines {
+        TakeNum(num) if *num <= 0 => {
+            let capacity = num.unsigned_abs();
+            if capacity == 0 {
+                return Ok(());
+            }
+            let mut ring = VecDeque::new();
+            loop {
+                if file.read_until(delimiter, &mut line)? == 0 {
+                    break;
+                }
+                ring.push_back(mem::take(&mut line));
+                if ring.len() as u64 > capacity {
+                    // Reuse the allocation of the line falling out
+                    line = ring.pop_front().unwrap();
+                    line.clear();
+                }
+            }
+            for line in ring {
+                out.write_all(&line)?;
+            }
+        }
+        _ => {
+            // +N starts at line N, so skip the ones before it
+            if let TakeNum(num) = num_lines {
+                for _ in 1..*num {
+                    if file.read_until(delimiter, &mut line)? == 0 {
+                        break;
+                    }
+                    line.clear();
+                }
+            }
+            io::copy(&mut file, out)?;
+        }
+    }
+    Ok(())
+}
+
+// Print bytes from an input that can only be read once
+fn print_stream_bytes(
+    mut file: impl BufRead,
+    out: &mut impl Write,
+    num_bytes: &TakeValue,
+    chars: bool,
+) -> MyResult<()> {
+    match num_bytes {
+        TakeNum(num) if *num <= 0 => {
+            let capacity = num.unsigned_abs() as usize;
+            if capacity == 0 {
+                return Ok(());
+            }
+            let mut ring = VecDeque::new();
+            let mut block = vec![0; BLOCK_SIZE];
+            loop {
+                let bytes_read = file.read(&mut block)?;
+                if bytes_read == 0 {
+                    break;
+                }
+                ring.extend(&block[..bytes_read]);
+                if ring.len() > capacity {
+                    ring.drain(..ring.len() - capacity);
+                }
+            }
+            let mut tail = &*ring.make_contiguous();
+            if chars {
+                skip_continuation_bytes(&mut tail)?;
+            }
+            out.write_all(tail)?;
+        }
+        _ => {
+            if let TakeNum(num) = num_bytes {
+                io::copy(&mut file.by_ref().take(*num as u64 - 1), &mut io::sink())?;
+            }
+            if chars {
+                skip_continuation_bytes(&mut file)?;
+            }
+            io::copy(&mut file, out)?;
+        }
+    }
+    Ok(())
+}
+
+fn print_bytes<T: BufRead + Seek>(
     mut file: T,
+    out: &mut impl Write,
     num_bytes: &TakeValue,
     total_bytes: i64,
+    chars: bool,
 ) -> MyResult<()> {
     // I still dont get why we have to do this sometimes...
     // maybe because of type safety so that we ensure there is always a Some()?
     if let Some(start) = get_start_index(num_bytes, total_bytes) {
         // Seek (repositioning the cursor) to an offset in a stream?
         file.seek(SeekFrom::Start(start))?;
-        let mut buffer = Vec::new();
-        file.read_to_end(&mut buffer)?;
-        if !buffer.is_empty() {
-            print!("{}", String::from_utf8_lossy(&buffer));
+        if chars {
+            skip_continuation_bytes(&mut file)?;
         }
+        // Bytes go out untouched, binary data included
+        io::copy(&mut file, out)?;
     }
 
     Ok(())
 }
 
+// A UTF-8 continuation byte looks like 0b10xxxxxx
+fn is_continuation(byte: u8) -> bool {
+    byte & 0b1100_0000 == 0b1000_0000
+}
+
+// Move past a partial character so the output starts on a boundary.
+// A character has at most three continuation bytes.
+fn skip_continuation_bytes(file: &mut impl BufRead) -> MyResult<()> {
+    for _ in 0..3 {
+        match file.fill_buf()?.first() {
+            Some(byte) if is_continuation(*byte) => file.consume(1),
+            _ => break,
+        }
+    }
+    Ok(())
+}
+
 // Find the starting byte position to query
 fn get_start_index(take_val: &TakeValue, total: i64) -> Option<u64> {
     match take_val {
@@ -235,7 +645,12 @@ fn get_start_index(take_val: &TakeValue, total: i64) -> Option<u64> {
 
 #[cfg(test)]
 mod tests {
-    use super::{TakeValue::*, count_lines_bytes, get_start_index, parse_num};
+    use super::{
+        TakeValue::*, count_lines_bytes, find_tail_start, get_start_index, parse_interval,
+        parse_num,
+    };
+    use std::io::Cursor;
+    use std::time::Duration;
 
     #[test]
     fn test_parse_num() {
@@ -277,6 +692,23 @@ mod tests {
         assert!(res.is_ok());
         assert_eq!(res.unwrap(), TakeNum(i64::MIN));
 
+        // Size suffixes multiply the number
+        assert_eq!(parse_num("2K").unwrap(), TakeNum(-2048));
+        assert_eq!(parse_num("+2K").unwrap(), TakeNum(2048));
+        assert_eq!(parse_num("2KiB").unwrap(), TakeNum(-2048));
+        assert_eq!(parse_num("2KB").unwrap(), TakeNum(-2000));
+        assert_eq!(parse_num("3b").unwrap(), TakeNum(-1536));
+        assert_eq!(parse_num("1M").unwrap(), TakeNum(-1_048_576));
+        assert_eq!(parse_num("1G").unwrap(), TakeNum(-1_073_741_824));
+        assert_eq!(parse_num("+0K").unwrap(), PlusZero);
+
+        // Unknown suffixes and overflowing sizes are invalid
+        let res = parse_num("3X");
+        assert!(res.is_err());
+        assert_eq!(res.unwrap_err().to_string(), "3X");
+        let res = parse_num(&format!("{}K", i64::MAX));
+        assert!(res.is_err());
+
         // A floating-point value is invalid
         let res = parse_num("3.14");
         assert!(res.is_err());
@@ -289,10 +721,10 @@ mod tests {
 
     #[test]
     fn test_count_lines_bytes() {
-        let res = count_lines_bytes("tests/inputs/one.txt");
+        let res = count_lines_bytes("tests/inputs/one.txt", b'\n');
         assert!(res.is_ok());
         assert_eq!(res.unwrap(), (1, 24));
-        let res = count_lines_bytes("tests/inputs/ten.txt");
+        let res = count_lines_bytes("tests/inputs/ten.txt", b'\n');
         assert!(res.is_ok());
         assert_eq!(res.unwrap(), (10, 49));
     }
@@ -332,4 +764,46 @@ mod tests {
         // return 0 to print the whole file
         assert_eq!(get_start_index(&TakeNum(-20), 10), Some(0));
     }
+
+    #[test]
+    fn test_find_tail_start() {
+        let mut text = Cursor::new("one\ntwo\nthree\n");
+        assert_eq!(find_tail_start(&mut text, 1, b'\n').unwrap(), 8);
+        assert_eq!(find_tail_start(&mut text, 2, b'\n').unwrap(), 4);
+        assert_eq!(find_tail_start(&mut text, 3, b'\n').unwrap(), 0);
+        assert_eq!(find_tail_start(&mut text, 4, b'\n').unwrap(), 0);
+
+        // Without a trailing newline the last partial line still counts
+        let mut text = Cursor::new("one\ntwo");
+        assert_eq!(find_tail_start(&mut text, 1, b'\n').unwrap(), 4);
+        assert_eq!(find_tail_start(&mut text, 2, b'\n').unwrap(), 0);
+
+        // Blank lines are lines too
+        let mut text = Cursor::new("a\n\n\n");
+        assert_eq!(find_tail_start(&mut text, 1, b'\n').unwrap(), 3);
+        assert_eq!(find_tail_start(&mut text, 2, b'\n').unwrap(), 2);
+
+        let mut text = Cursor::new("");
+        assert_eq!(find_tail_start(&mut text, 1, b'\n').unwrap(), 0);
+
+        // Lines spanning several blocks
+        let long = format!("{}\n{}\n", "x".repeat(100_000), "y".repeat(70_000));
+        let mut text = Cursor::new(long);
+        assert_eq!(find_tail_start(&mut text, 1, b'\n').unwrap(), 100_001);
+        assert_eq!(find_tail_start(&mut text, 2, b'\n').unwrap(), 0);
+    }
+
+    #[test]
+    fn test_parse_interval() {
+        assert_eq!(parse_interval("1").unwrap(), Duration::from_secs(1));
+        assert_eq!(parse_interval("0.25").unwrap(), Duration::from_millis(250));
+        assert_eq!(parse_interval("0").unwrap(), Duration::ZERO);
+
+        // Negative, non-numeric and infinite intervals are invalid
+        let res = parse_interval("-1");
+        assert!(res.is_err());
+        assert_eq!(res.unwrap_err().to_string(), "-1");
+        assert!(parse_interval("foo").is_err());
+        assert!(parse_interval("inf").is_err());
+    }
 }
@@ -0,0 +1,65 @@
+use std::io::{self, Write};
+
+// ANSI colors cycled through for the labels of successive files
+const COLORS: [u8; 6] = [32, 33, 34, 35, 36, 31];
+
+// Build the `[name] ` label put in front of every line of a file
+pub fn label(name: &str, file_num: usize, color: bool) -> Vec<u8> {
+    if color {
+        let code = COLORS[file_num % COLORS.len()];
+        format!("\x1b[{}m[{}]\x1b[0m ", code, name).into_bytes()
+    } else {
+        format!("[{}] ", name).into_bytes()
+    }
+}
+
+// Writer that puts a label in front of every line passing through it.
+// Without a label everything is passed through untouched.
+pub struct PrefixWriter<'a, W: Write> {
+    inner: W,
+    label: Option<&'a [u8]>,
+    delimiter: u8,
+    // Whether the next byte written starts a new line
+    line_start: bool,
+}
+
+impl<'a, W: Write> PrefixWriter<'a, W> {
+    pub fn new(inner: W, label: Option<&'a [u8]>, delimiter: u8) -> Self {
+        Self {
+            inner,
+            label,
+            delimiter,
+            line_start: true,
+        }
+    }
+
+    // Terminate a last line that had no delimiter,
+    // so the next label does not end up in the middle of it
+    pub fn finish(&mut self) -> io::Result<()> {
+        if self.label.is_some() && !self.line_start {
+            self.inner.write_all(&[self.delimiter])?;
+            self.line_start = true;
+        }
+        Ok(())
+    }
+}
+
+impl<W: Write> Write for PrefixWriter<'_, W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let Some(label) = self.label else {
+            return self.inner.write(buf);
+        };
+        for line in buf.split_inclusive(|byte| *byte == self.delimiter) {
+            if self.line_start {
+                self.inner.write_all(label)?;
+            }
+            self.inner.write_all(line)?;
+            self.line_start = line.last() == Some(&self.delimiter);
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
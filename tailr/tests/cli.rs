@@ -3,8 +3,13 @@ use assert_cmd::Command;
 use predicates::prelude::*;
 use pretty_assertions::assert_eq;
 use rand::{Rng, distributions::Alphanumeric};
-use std::fs::{self, File};
-use std::io::Read;
+use std::fs::{self, File, OpenOptions};
+use std::io::{Read, Write};
+use std::path::Path;
+use std::process::Stdio;
+use std::thread;
+use std::time::Duration;
+use tempfile::tempdir;
 
 const PRG: &str = "tailr";
 const EMPTY: &str = "tests/inputs/empty.txt";
@@ -34,11 +39,14 @@ fn gen_bad_file() -> String {
 
 // --------------------------------------------------
 #[test]
-fn dies_no_args() -> Result<()> {
+fn no_args_reads_stdin() -> Result<()> {
+    let input = fs::read_to_string(TWELVE)?;
+    let expected = fs::read_to_string("tests/expected/twelve.txt.out")?;
     Command::cargo_bin(PRG)?
+        .write_stdin(input)
         .assert()
-        .failure()
-        .stderr(predicate::str::contains("Usage"));
+        .success()
+        .stdout(expected);
 
     Ok(())
 }
@@ -114,6 +122,22 @@ fn run(args: &[&str], expected_file: &str) -> Result<()> {
     Ok(())
 }
 
+// --------------------------------------------------
+fn run_stdin(args: &[&str], input_file: &str, expected_file: &str) -> Result<()> {
+    let input = fs::read(input_file)?;
+    let expected = fs::read(expected_file)?;
+
+    let output = Command::cargo_bin(PRG)?
+        .args(args)
+        .write_stdin(input)
+        .output()
+        .expect("fail");
+    assert!(output.status.success());
+    assert_eq!(output.stdout, expected);
+
+    Ok(())
+}
+
 // --------------------------------------------------
 #[test]
 fn empty() -> Result<()> {
@@ -828,3 +852,530 @@ fn multiple_files_c_plus_3() -> Result<()> {
         "tests/expected/all.c+3.out",
     )
 }
+
+// --------------------------------------------------
+// Start tailr in follow mode, run `action` against the followed files
+// from another thread and return whatever was printed before it is killed
+fn follow(args: &[&str], action: impl FnOnce() + Send + 'static) -> Result<String> {
+    let mut child = std::process::Command::new(env!("CARGO_BIN_EXE_tailr"))
+        .args(args)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()?;
+    let writer = thread::spawn(move || {
+        thread::sleep(Duration::from_millis(500));
+        action();
+    });
+    writer.join().unwrap();
+    thread::sleep(Duration::from_millis(2500));
+    child.kill()?;
+    let output = child.wait_with_output()?;
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+fn append(path: &Path, text: &str) {
+    let mut file = OpenOptions::new().append(true).open(path).unwrap();
+    file.write_all(text.as_bytes()).unwrap();
+}
+
+// --------------------------------------------------
+#[test]
+fn follow_appended_lines() -> Result<()> {
+    let dir = tempdir()?;
+    let path = dir.path().join("log.txt");
+    fs::write(&path, "one\ntwo\n")?;
+
+    let appended = path.clone();
+    let out = follow(&["-f", "-n", "1", path.to_str().unwrap()], move || {
+        append(&appended, "three\n");
+        append(&appended, "four\n");
+    })?;
+    assert_eq!(out, "two\nthree\nfour\n");
+
+    Ok(())
+}
+
+#[test]
+fn follow_after_empty_snapshot() -> Result<()> {
+    // Nothing of the file is printed, following still starts at its end
+    let dir = tempdir()?;
+    let path = dir.path().join("log.txt");
+    fs::write(&path, "one\ntwo\n")?;
+
+    let appended = path.clone();
+    let out = follow(&["-f", "-n", "0", path.to_str().unwrap()], move || {
+        append(&appended, "three\n");
+    })?;
+    assert_eq!(out, "three\n");
+
+    Ok(())
+}
+
+#[test]
+fn follow_truncated_file() -> Result<()> {
+    let dir = tempdir()?;
+    let path = dir.path().join("log.txt");
+    fs::write(&path, "one\ntwo\n")?;
+
+    let truncated = path.clone();
+    let out = follow(&["-f", path.to_str().unwrap()], move || {
+        fs::write(&truncated, "new\n").unwrap();
+    })?;
+    assert_eq!(out, "one\ntwo\nnew\n");
+
+    Ok(())
+}
+
+#[test]
+fn follow_multiple_files_headers() -> Result<()> {
+    let dir = tempdir()?;
+    let first = dir.path().join("a.txt");
+    let second = dir.path().join("b.txt");
+    fs::write(&first, "a1\n")?;
+    fs::write(&second, "b1\n")?;
+
+    let (a, b) = (first.clone(), second.clone());
+    let out = follow(
+        &["-f", first.to_str().unwrap(), second.to_str().unwrap()],
+        move || {
+            append(&a, "a2\n");
+            thread::sleep(Duration::from_millis(1500));
+            append(&b, "b2\n");
+        },
+    )?;
+    let expected = format!(
+        "==> {a} <==\na1\n\n==> {b} <==\nb1\n\n==> {a} <==\na2\n\n==> {b} <==\nb2\n",
+        a = first.display(),
+        b = second.display()
+    );
+    assert_eq!(out, expected);
+
+    Ok(())
+}
+
+#[test]
+fn follow_header_after_stdin() -> Result<()> {
+    let dir = tempdir()?;
+    let path = dir.path().join("a.txt");
+    fs::write(&path, "a1\n")?;
+
+    let appended = path.clone();
+    let out = follow(&["-f", path.to_str().unwrap(), "-"], move || {
+        append(&appended, "a2\n");
+    })?;
+    // Standard input was printed last, so the file needs its header again
+    let expected = format!(
+        "==> {a} <==\na1\n\n==> standard input <==\n\n==> {a} <==\na2\n",
+        a = path.display()
+    );
+    assert_eq!(out, expected);
+
+    Ok(())
+}
+
+#[test]
+fn follow_name_reopens_rotated_file() -> Result<()> {
+    let dir = tempdir()?;
+    let path = dir.path().join("log.txt");
+    fs::write(&path, "old\n")?;
+
+    let rotated = path.clone();
+    let out = follow(&["-F", path.to_str().unwrap()], move || {
+        fs::rename(&rotated, rotated.with_extension("1")).unwrap();
+        fs::write(&rotated, "fresh\n").unwrap();
+    })?;
+    assert_eq!(out, "old\nfresh\n");
+
+    Ok(())
+}
+
+#[test]
+fn follow_descriptor_ignores_rotation() -> Result<()> {
+    let dir = tempdir()?;
+    let path = dir.path().join("log.txt");
+    fs::write(&path, "old\n")?;
+
+    let rotated = path.clone();
+    let out = follow(&["-f", path.to_str().unwrap()], move || {
+        let moved = rotated.with_extension("1");
+        fs::rename(&rotated, &moved).unwrap();
+        fs::write(&rotated, "fresh\n").unwrap();
+        append(&moved, "still old\n");
+    })?;
+    assert_eq!(out, "old\nstill old\n");
+
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn stdin_n3() -> Result<()> {
+    run_stdin(
+        &["-n", "3", "-"],
+        TWELVE,
+        "tests/expected/twelve.txt.n3.out",
+    )
+}
+
+#[test]
+fn stdin_n_plus_2() -> Result<()> {
+    run_stdin(&["-n", "+2"], TWELVE, "tests/expected/twelve.txt.n+2.out")
+}
+
+#[test]
+fn stdin_n_plus_0() -> Result<()> {
+    run_stdin(&["-n", "+0"], TWELVE, "tests/expected/twelve.txt.n+0.out")
+}
+
+#[test]
+fn stdin_n0() -> Result<()> {
+    run_stdin(&["-n", "0"], TWELVE, "tests/expected/twelve.txt.n0.out")
+}
+
+#[test]
+fn stdin_n200() -> Result<()> {
+    run_stdin(&["-n", "200"], TWELVE, "tests/expected/twelve.txt.n200.out")
+}
+
+#[test]
+fn stdin_c8() -> Result<()> {
+    run_stdin(&["-c", "8"], TWELVE, "tests/expected/twelve.txt.c8.out")
+}
+
+#[test]
+fn stdin_c_plus_2() -> Result<()> {
+    run_stdin(&["-c", "+2"], TWELVE, "tests/expected/twelve.txt.c+2.out")
+}
+
+#[test]
+fn stdin_c200() -> Result<()> {
+    run_stdin(&["-c", "200"], TWELVE, "tests/expected/twelve.txt.c200.out")
+}
+
+#[test]
+fn stdin_empty() -> Result<()> {
+    run_stdin(&["-n", "3"], EMPTY, "tests/expected/empty.txt.n3.out")
+}
+
+#[test]
+fn stdin_with_files_header() -> Result<()> {
+    let expected = format!(
+        "==> {ONE} <==\n{}\n==> standard input <==\n{}",
+        fs::read_to_string(ONE)?,
+        fs::read_to_string(TWO)?
+    );
+    Command::cargo_bin(PRG)?
+        .args([ONE, "-"])
+        .write_stdin(fs::read(TWO)?)
+        .assert()
+        .success()
+        .stdout(expected);
+
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn verbose_single_file() -> Result<()> {
+    let expected = format!("==> {ONE} <==\n{}", fs::read_to_string(ONE)?);
+    for flag in ["-v", "--verbose"] {
+        Command::cargo_bin(PRG)?
+            .args([flag, ONE])
+            .assert()
+            .success()
+            .stdout(expected.clone());
+    }
+
+    Ok(())
+}
+
+#[test]
+fn verbose_overrides_quiet() -> Result<()> {
+    let expected = format!("==> {ONE} <==\n{}", fs::read_to_string(ONE)?);
+    Command::cargo_bin(PRG)?
+        .args(["-q", "-v", ONE])
+        .assert()
+        .success()
+        .stdout(expected);
+
+    Ok(())
+}
+
+#[test]
+fn zero_terminated() -> Result<()> {
+    let dir = tempdir()?;
+    let path = dir.path().join("records");
+    fs::write(&path, "a\nb\0c\nd\0e\0f")?;
+    let path = path.to_str().unwrap();
+
+    for flag in ["-z", "--zero-terminated"] {
+        Command::cargo_bin(PRG)?
+            .args([flag, "-n", "2", path])
+            .assert()
+            .success()
+            .stdout("e\0f");
+        Command::cargo_bin(PRG)?
+            .args([flag, "-n", "+2", path])
+            .assert()
+            .success()
+            .stdout("c\nd\0e\0f");
+        Command::cargo_bin(PRG)?
+            .args([flag, "-n", "3"])
+            .write_stdin("a\nb\0c\nd\0e\0f")
+            .assert()
+            .success()
+            .stdout("c\nd\0e\0f");
+    }
+
+    Ok(())
+}
+
+#[test]
+fn size_suffixes() -> Result<()> {
+    let dir = tempdir()?;
+    let path = dir.path().join("big");
+    let content: Vec<u8> = (0..3000).map(|i| b'a' + (i % 26) as u8).collect();
+    fs::write(&path, &content)?;
+    let path = path.to_str().unwrap();
+
+    Command::cargo_bin(PRG)?
+        .args(["-c", "1b", path])
+        .assert()
+        .success()
+        .stdout(predicate::eq(&content[3000 - 512..]));
+    Command::cargo_bin(PRG)?
+        .args(["-c", "2K", path])
+        .assert()
+        .success()
+        .stdout(predicate::eq(&content[3000 - 2048..]));
+    Command::cargo_bin(PRG)?
+        .args(["-c", "+2KB", path])
+        .assert()
+        .success()
+        .stdout(predicate::eq(&content[1999..]));
+
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn bytes_are_not_lossy() -> Result<()> {
+    let dir = tempdir()?;
+    let path = dir.path().join("binary");
+    let content: Vec<u8> = (0..=255).collect();
+    fs::write(&path, &content)?;
+
+    Command::cargo_bin(PRG)?
+        .args(["-c", "100", path.to_str().unwrap()])
+        .assert()
+        .success()
+        .stdout(predicate::eq(&content[156..]));
+    Command::cargo_bin(PRG)?
+        .args(["-c", "100"])
+        .write_stdin(content.clone())
+        .assert()
+        .success()
+        .stdout(predicate::eq(&content[156..]));
+    Command::cargo_bin(PRG)?
+        .args(["-n", "1", path.to_str().unwrap()])
+        .assert()
+        .success()
+        .stdout(predicate::eq(&content[11..]));
+
+    Ok(())
+}
+
+#[test]
+fn chars_aligns_to_boundary() -> Result<()> {
+    let dir = tempdir()?;
+    let path = dir.path().join("utf8");
+    // Taking 5 bytes lands in the middle of "é"
+    fs::write(&path, "abcdé€")?;
+    let path = path.to_str().unwrap();
+
+    Command::cargo_bin(PRG)?
+        .args(["-c", "4", path])
+        .assert()
+        .success()
+        .stdout(predicate::eq(&"abcdé€".as_bytes()[5..]));
+    Command::cargo_bin(PRG)?
+        .args(["-c", "4", "--chars", path])
+        .assert()
+        .success()
+        .stdout("€");
+    Command::cargo_bin(PRG)?
+        .args(["-c", "5", "--chars", path])
+        .assert()
+        .success()
+        .stdout("é€");
+    Command::cargo_bin(PRG)?
+        .args(["-c", "+6", "--chars", path])
+        .assert()
+        .success()
+        .stdout("€");
+    Command::cargo_bin(PRG)?
+        .args(["-c", "4", "--chars"])
+        .write_stdin("abcdé€")
+        .assert()
+        .success()
+        .stdout("€");
+
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn follow_exits_with_pid() -> Result<()> {
+    let dir = tempdir()?;
+    let path = dir.path().join("build.log");
+    fs::write(&path, "start\n")?;
+
+    // The last line is written by the process itself, before it exits
+    let mut build = std::process::Command::new("sh")
+        .args([
+            "-c",
+            "sleep 0.3; echo building >> \"$1\"; sleep 0.7; echo done >> \"$1\"",
+        ])
+        .args(["sh", path.to_str().unwrap()])
+        .spawn()?;
+    let pid = build.id().to_string();
+    // Reap the process so it does not linger as a zombie
+    let writer = thread::spawn(move || build.wait().unwrap());
+
+    Command::cargo_bin(PRG)?
+        .args(["-f", "-s", "0.1", "--pid", &pid, path.to_str().unwrap()])
+        .timeout(Duration::from_secs(10))
+        .assert()
+        .success()
+        .stdout("start\nbuilding\ndone\n");
+    writer.join().unwrap();
+
+    Ok(())
+}
+
+#[test]
+fn follow_nothing_remaining() -> Result<()> {
+    let bad = gen_bad_file();
+    Command::cargo_bin(PRG)?
+        .args(["-f", &bad])
+        .timeout(Duration::from_secs(10))
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("no files remaining"));
+
+    Ok(())
+}
+
+#[test]
+fn follow_stdin_pipe() -> Result<()> {
+    // Nothing more can be written to a pipe that is already closed
+    Command::cargo_bin(PRG)?
+        .arg("-f")
+        .write_stdin("a\nb\n")
+        .timeout(Duration::from_secs(10))
+        .assert()
+        .success()
+        .stdout("a\nb\n");
+
+    Ok(())
+}
+
+#[test]
+fn dies_bad_sleep_interval() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args(["-f", "-s", "soon", ONE])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "invalid number of seconds -- soon",
+        ));
+
+    Ok(())
+}
+
+#[test]
+fn dies_bad_pid() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args(["-f", "--pid=0", ONE])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("invalid PID -- 0"));
+
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn prefix_lines() -> Result<()> {
+    // No headers, and empty files print nothing at all
+    Command::cargo_bin(PRG)?
+        .args(["--prefix", "-n", "1", TWO, EMPTY, THREE])
+        .assert()
+        .success()
+        .stdout(format!("[{TWO}] Four words.\n[{THREE}] four words.\n"));
+
+    Ok(())
+}
+
+#[test]
+fn prefix_stdin_and_partial_line() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args(["--prefix", "-n", "2", "-", TWO])
+        .write_stdin("one\ntwo\nthree")
+        .assert()
+        .success()
+        .stdout(format!(
+            "[standard input] two\n[standard input] three\n\
+             [{TWO}] Two lines.\n[{TWO}] Four words.\n"
+        ));
+
+    Ok(())
+}
+
+#[test]
+fn prefix_color() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args(["--prefix", "--color", "-n", "1", ONE, TWO])
+        .assert()
+        .success()
+        .stdout(format!(
+            "\x1b[32m[{ONE}]\x1b[0m {}\x1b[33m[{TWO}]\x1b[0m Four words.\n",
+            fs::read_to_string(ONE)?
+        ));
+
+    Ok(())
+}
+
+#[test]
+fn follow_prefix_interleaves_lines() -> Result<()> {
+    let dir = tempdir()?;
+    let first = dir.path().join("a.log");
+    let second = dir.path().join("b.log");
+    fs::write(&first, "a1\n")?;
+    fs::write(&second, "b1\n")?;
+
+    let (a, b) = (first.clone(), second.clone());
+    let out = follow(
+        &[
+            "-f",
+            "--prefix",
+            first.to_str().unwrap(),
+            second.to_str().unwrap(),
+        ],
+        move || {
+            append(&b, "b2\n");
+            // A partial line is held back until it is complete
+            append(&a, "a2 ");
+            thread::sleep(Duration::from_millis(1500));
+            append(&a, "done\n");
+        },
+    )?;
+    let (a, b) = (first.display(), second.display());
+    assert_eq!(
+        out,
+        format!("[{a}] a1\n[{b}] b1\n[{b}] b2\n[{a}] a2 done\n")
+    );
+
+    Ok(())
+}
@@ -19,10 +19,15 @@ apple
 banana
 
 * */
+mod seen;
+
 use clap::{App, Arg};
+use seen::{FirstSeen, Seen};
 use std::{
+    collections::{HashMap, hash_map::Entry},
     error::Error,
     fs::File,
+    hash::{DefaultHasher, Hash, Hasher},
     io::{self, BufRead, BufReader, Write},
 };
 
@@ -34,6 +39,75 @@ pub struct Config {
     // Output file is optional
     out_file: Option<String>,
     count: bool,
+    // -d: only print lines that are repeated
+    repeated: bool,
+    // -u: only print lines that are not repeated
+    unique: bool,
+    // -D: print every line of a repeated run
+    all_repeated: Option<Delimit>,
+    // --group: print every line, with blank lines around the runs
+    group: Option<Delimit>,
+    ignore_case: bool,
+    skip_fields: usize,
+    skip_chars: usize,
+    // Only compare this many characters
+    check_chars: Option<usize>,
+    // Drop every repeat of a line, not only adjacent ones
+    global: bool,
+    // Bytes of bookkeeping for --global before it moves to disk
+    max_memory: Option<usize>,
+    // Ends every record, newline or NUL with -z
+    delimiter: u8,
+    count_format: CountFormat,
+    // Report the N most frequent lines of the whole input
+    top: Option<usize>,
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+enum CountFormat {
+    // Count right-aligned in front of the line
+    Padded,
+    // Count, a tab and the line
+    Tsv,
+    // One JSON object per line
+    Json,
+}
+
+// Where blank lines go with --all-repeated and --group
+#[derive(Debug, PartialEq, Clone, Copy)]
+enum Delimit {
+    None,
+    Prepend,
+    Separate,
+    Append,
+    Both,
+}
+
+impl Delimit {
+    fn parse(val: Option<&str>, default: Delimit) -> Delimit {
+        match val {
+            Some("none") => Delimit::None,
+            Some("prepend") => Delimit::Prepend,
+            Some("separate") => Delimit::Separate,
+            Some("append") => Delimit::Append,
+            Some("both") => Delimit::Both,
+            _ => default,
+        }
+    }
+
+    // Whether a blank line goes before a run
+    fn before(self, first: bool) -> bool {
+        match self {
+            Delimit::None => false,
+            Delimit::Prepend | Delimit::Both => true,
+            Delimit::Separate | Delimit::Append => !first,
+        }
+    }
+
+    // Whether a blank line goes after the last run
+    fn after_last(self) -> bool {
+        matches!(self, Delimit::Append | Delimit::Both)
+    }
 }
 
 fn main() {
@@ -66,8 +140,138 @@ fn get_args() -> MyResult<Config> {
                 .long("count")
                 .takes_value(false),
         )
+        .arg(
+            Arg::with_name("repeated")
+                .short("d")
+                .long("repeated")
+                .help("Only print duplicate lines, one for each group")
+                .takes_value(false),
+        )
+        .arg(
+            Arg::with_name("unique")
+                .short("u")
+                .long("unique")
+                .help("Only print unique lines")
+                .takes_value(false),
+        )
+        .arg(
+            Arg::with_name("all_repeated")
+                .short("D")
+                .long("all-repeated")
+                .value_name("METHOD")
+                .help("Print all duplicate lines, delimited with none (default), prepend or separate")
+                .takes_value(true)
+                .min_values(0)
+                .require_equals(true)
+                .possible_values(&["none", "prepend", "separate"])
+                .conflicts_with("count"),
+        )
+        .arg(
+            Arg::with_name("group")
+                .long("group")
+                .value_name("METHOD")
+                .help("Print all lines, separating runs with separate (default), prepend, append or both")
+                .takes_value(true)
+                .min_values(0)
+                .require_equals(true)
+                .possible_values(&["separate", "prepend", "append", "both"])
+                .conflicts_with_all(&["count", "repeated", "unique", "all_repeated"]),
+        )
+        .arg(
+            Arg::with_name("ignore_case")
+                .short("i")
+                .long("ignore-case")
+                .help("Ignore differences in case when comparing")
+                .takes_value(false),
+        )
+        .arg(
+            Arg::with_name("skip_fields")
+                .short("f")
+                .long("skip-fields")
+                .value_name("N")
+                .help("Avoid comparing the first N fields")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("skip_chars")
+                .short("s")
+                .long("skip-chars")
+                .value_name("N")
+                .help("Avoid comparing the first N characters")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("check_chars")
+                .short("w")
+                .long("check-chars")
+                .value_name("N")
+                .help("Compare no more than N characters in lines")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("zero_terminated")
+                .short("z")
+                .long("zero-terminated")
+                .help("Line delimiter is NUL, not newline")
+                .takes_value(false),
+        )
+        .arg(
+            Arg::with_name("global")
+                .long("global")
+                .help("Only print the first occurrence of each line, wherever it appears")
+                .takes_value(false)
+                .conflicts_with_all(&["repeated", "unique", "all_repeated", "group"]),
+        )
+        .arg(
+            Arg::with_name("max_memory")
+                .long("max-memory")
+                .value_name("SIZE")
+                .help("Spill --global bookkeeping to disk past SIZE bytes (K, M, G suffixes)")
+                .takes_value(true)
+                .requires("global"),
+        )
+        .arg(
+            Arg::with_name("count_format")
+                .long("count-format")
+                .value_name("FORMAT")
+                .help("Print counts as padded (default), tsv or json, implies -c")
+                .possible_values(&["padded", "tsv", "json"])
+                .takes_value(true)
+                .conflicts_with_all(&["all_repeated", "group"]),
+        )
+        .arg(
+            Arg::with_name("top")
+                .long("top")
+                .value_name("N")
+                .help("Print the N most frequent lines with their counts")
+                .takes_value(true)
+                .conflicts_with_all(&["repeated", "unique", "all_repeated", "group", "global"]),
+        )
         .get_matches();
 
+    let skip_fields = matches
+        .value_of("skip_fields")
+        .map(|val| parse_number(val, "fields to skip"))
+        .transpose()?;
+    let skip_chars = matches
+        .value_of("skip_chars")
+        .map(|val| parse_number(val, "characters to skip"))
+        .transpose()?;
+    let check_chars = matches
+        .value_of("check_chars")
+        .map(|val| parse_number(val, "characters to compare"))
+        .transpose()?;
+    let max_memory = matches.value_of("max_memory").map(parse_size).transpose()?;
+    let top = matches
+        .value_of("top")
+        .map(|val| parse_number(val, "lines to report"))
+        .transpose()?;
+    let count_format = match matches.value_of("count_format") {
+        Some("tsv") => CountFormat::Tsv,
+        Some("json") => CountFormat::Json,
+        _ => CountFormat::Padded,
+    };
+
     Ok(Config {
         // Alternatives
         // 1. Apply String::from to the file
@@ -82,10 +286,112 @@ fn get_args() -> MyResult<Config> {
         // Alternative with anon function as closure
         // out_file: matches.value_of("out_file").map(|v| v.to_string()),
         out_file: matches.value_of("out_file").map(String::from),
-        count: matches.is_present("count"),
+        count: matches.is_present("count") || matches.is_present("count_format") || top.is_some(),
+        repeated: matches.is_present("repeated"),
+        unique: matches.is_present("unique"),
+        all_repeated: matches
+            .is_present("all_repeated")
+            .then(|| Delimit::parse(matches.value_of("all_repeated"), Delimit::None)),
+        group: matches
+            .is_present("group")
+            .then(|| Delimit::parse(matches.value_of("group"), Delimit::Separate)),
+        ignore_case: matches.is_present("ignore_case"),
+        skip_fields: skip_fields.unwrap_or(0),
+        skip_chars: skip_chars.unwrap_or(0),
+        check_chars,
+        global: matches.is_present("global"),
+        max_memory,
+        delimiter: if matches.is_present("zero_terminated") {
+            b'\0'
+        } else {
+            b'\n'
+        },
+        count_format,
+        top,
     })
 }
 
+fn parse_size(val: &str) -> MyResult<usize> {
+    let (digits, unit) = match val.find(|ch: char| !ch.is_ascii_digit()) {
+        Some(pos) => val.split_at(pos),
+        None => (val, ""),
+    };
+    let multiplier = match unit {
+        "" => 1,
+        "K" | "k" => 1 << 10,
+        "M" => 1 << 20,
+        "G" => 1 << 30,
+        _ => return Err(From::from(format!("invalid memory size -- '{}'", val))),
+    };
+    digits
+        .parse::<usize>()
+        .ok()
+        .and_then(|num| num.checked_mul(multiplier))
+        .ok_or_else(|| From::from(format!("invalid memory size -- '{}'", val)))
+}
+
+fn parse_number(val: &str, what: &str) -> MyResult<usize> {
+    val.parse()
+        .map_err(|_| From::from(format!("invalid number of {} -- '{}'", what, val)))
+}
+
+// The part of a line that decides whether it equals its neighbour,
+// after applying -f, -s, -w and -i in the same order as GNU uniq.
+// Only the record terminator is left out, everything else counts.
+fn comparison_key(line: &[u8], config: &Config) -> Vec<u8> {
+    let mut rest = line.strip_suffix(&[config.delimiter]).unwrap_or(line);
+    // A field is a run of blanks followed by a run of non-blanks
+    let is_blank = |byte: &u8| *byte == b' ' || *byte == b'\t';
+    for _ in 0..config.skip_fields {
+        let start = rest.iter().position(|byte| !is_blank(byte));
+        rest = &rest[start.unwrap_or(rest.len())..];
+        let end = rest.iter().position(is_blank);
+        rest = &rest[end.unwrap_or(rest.len())..];
+    }
+    if config.skip_chars == 0 && config.check_chars.is_none() && !config.ignore_case {
+        return rest.to_vec();
+    }
+
+    // These work on characters. Invalid UTF-8 is kept as it is,
+    // every invalid byte counting as one character.
+    let mut skip = config.skip_chars;
+    let mut keep = config.check_chars.unwrap_or(usize::MAX);
+    let mut key = Vec::new();
+    for chunk in rest.utf8_chunks() {
+        let valid = chunk.valid();
+        let start = advance(valid, &mut skip);
+        let end = start + advance(&valid[start..], &mut keep);
+        if config.ignore_case {
+            key.extend_from_slice(valid[start..end].to_lowercase().as_bytes());
+        } else {
+            key.extend_from_slice(&valid.as_bytes()[start..end]);
+        }
+
+        let invalid = chunk.invalid();
+        let start = skip.min(invalid.len());
+        skip -= start;
+        let end = start + keep.min(invalid.len() - start);
+        keep -= end - start;
+        key.extend_from_slice(&invalid[start..end]);
+    }
+    key
+}
+
+// Byte offset after up to `n` characters of `text`,
+// `n` goes down by the number of characters passed
+fn advance(text: &str, n: &mut usize) -> usize {
+    match text.char_indices().nth(*n) {
+        Some((offset, _)) => {
+            *n = 0;
+            offset
+        }
+        None => {
+            *n -= text.chars().count();
+            text.len()
+        }
+    }
+}
+
 fn run(config: Config) -> MyResult<()> {
     let mut file = open(&config.in_file).map_err(|e| format!("{}: {}", config.in_file, e))?;
 
@@ -97,60 +403,323 @@ fn run(config: Config) -> MyResult<()> {
         _ => Box::new(io::stdout()),
     };
 
+    if let Some(n) = config.top {
+        return run_top(&config, file, &mut out_file, n);
+    }
+    if config.global {
+        return run_global(&config, file, &mut out_file);
+    }
+
+    // Whether every line of a run is printed, not just the first
+    let print_all = config.all_repeated.or(config.group);
+    let mut runs_printed = 0;
+
     // WE USE A CLOSURE :)
     // Now I get it: Closure is an anon func that accepts vars from its enclosing env.
     // Btw Rust's syntax for closure is weird IMO.
-    let mut print = |count: u64, text: &str| -> MyResult<()> {
+    let mut print = |run: &Run| -> MyResult<()> {
+        // -d and -D only keep runs with duplicates and -u only runs without
+        let wanted = match run.count {
+            0 => false,
+            1 => !config.repeated && config.all_repeated.is_none(),
+            _ => !config.unique,
+        };
         // Accepting count from outer env here
-        if count > 0 {
-            if config.count {
-                // why borrowed here???
-                write!(out_file, "{:>4} {}", count, text)?;
-            } else {
-                write!(out_file, "{}", text)?;
+        if !wanted {
+            return Ok(());
+        }
+        match print_all {
+            Some(delimit) => {
+                if delimit.before(runs_printed == 0) {
+                    out_file.write_all(&[config.delimiter])?;
+                }
+                for line in &run.lines {
+                    out_file.write_all(line)?;
+                }
             }
+            // why borrowed here???
+            None if config.count => write_count(&mut out_file, &config, run.count, &run.lines[0])?,
+            None => out_file.write_all(&run.lines[0])?,
         }
+        runs_printed += 1;
 
         Ok(())
     };
 
-    let mut line = String::new();
-    let mut previous = String::new();
-    // TIP: No need to declare a type
-    // since Rust can infer it?
-    let mut count: u64 = 0;
+    // Raw bytes, so invalid UTF-8 does not stop us
+    let mut line = Vec::new();
+    let mut run = Run::default();
 
     loop {
         // Append delimiter to buffer,
         // thus preserving line ending?
-        let bytes = file.read_line(&mut line)?;
+        let bytes = file.read_until(config.delimiter, &mut line)?;
         if bytes == 0 {
             // Done reading
             break;
         }
 
         // Calculate adjacent duplicate lines
-        if line.trim_end() != previous.trim_end() {
+        let key = comparison_key(&line, &config);
+        if run.count == 0 || key != run.key {
             // Encounter non-duplicate line,
-            // so we copy it for later comparison
-            // and reset the counter
+            // so we start a new run for later comparison
+            print(&run)?;
+            run = Run {
+                key,
+                lines: vec![line.clone()],
+                count: 0,
+            };
+        } else if print_all.is_some() {
+            run.lines.push(line.clone());
+        }
+
+        run.count += 1;
+        line.clear();
+    }
+
+    print(&run)?;
+    if runs_printed > 0 && print_all.is_some_and(Delimit::after_last) {
+        out_file.write_all(&[config.delimiter])?;
+    }
+    Ok(())
+}
+
+// The run of adjacent equal lines being read.
+// Only the first line is kept unless all of them get printed.
+#[derive(Debug, Default)]
+struct Run {
+    key: Vec<u8>,
+    lines: Vec<Vec<u8>>,
+    // TIP: No need to declare a type
+    // since Rust can infer it?
+    count: u64,
+}
+
+// Keep the first occurrence of every line, like `awk '!seen[$0]++'`.
+// Only hashes are remembered, unless -c needs the lines for the end.
+fn run_global(config: &Config, mut file: impl BufRead, out_file: &mut impl Write) -> MyResult<()> {
+    let mut seen = Seen::default();
+    let mut firsts = FirstSeen::default();
+    let mut line = Vec::new();
 
-            print(count, &previous)?;
-            previous = line.clone();
-            count = 0;
+    loop {
+        let bytes = file.read_until(config.delimiter, &mut line)?;
+        if bytes == 0 {
+            break;
         }
 
-        count += 1;
+        let mut hasher = DefaultHasher::new();
+        comparison_key(&line, config).hash(&mut hasher);
+        let hash = hasher.finish();
+        if seen.insert(hash)? {
+            if config.count {
+                firsts.push(hash, line.clone());
+            } else {
+                out_file.write_all(&line)?;
+            }
+        }
+        if let Some(limit) = config.max_memory
+            && seen.memory_used() + firsts.memory_used() > limit
+        {
+            seen.spill()?;
+            firsts.spill()?;
+        }
         line.clear();
     }
 
-    print(count, &previous)?;
+    firsts.for_each(|hash, text| -> MyResult<()> {
+        write_count(out_file, config, seen.count(hash)?, text)
+    })
+}
+
+// Report the most frequent lines, the most common first.
+// Lines seen equally often keep the order they first appeared in.
+fn run_top(
+    config: &Config,
+    mut file: impl BufRead,
+    out_file: &mut impl Write,
+    n: usize,
+) -> MyResult<()> {
+    // Index into `lines` for every key
+    let mut index: HashMap<Vec<u8>, usize> = HashMap::new();
+    // The first line seen for every key, with how often the key occurred
+    let mut lines: Vec<(Vec<u8>, u64)> = Vec::new();
+    let mut line = Vec::new();
+
+    loop {
+        let bytes = file.read_until(config.delimiter, &mut line)?;
+        if bytes == 0 {
+            break;
+        }
+        match index.entry(comparison_key(&line, config)) {
+            Entry::Occupied(entry) => lines[*entry.get()].1 += 1,
+            Entry::Vacant(entry) => {
+                entry.insert(lines.len());
+                lines.push((line.clone(), 1));
+            }
+        }
+        line.clear();
+    }
+
+    // A stable sort, so ties stay in first-seen order
+    lines.sort_by_key(|(_, count)| std::cmp::Reverse(*count));
+    for (text, count) in lines.iter().take(n) {
+        write_count(out_file, config, *count, text)?;
+    }
     Ok(())
 }
 
+// Write a line with the number of times it occurred in --count-format
+fn write_count(
+    out_file: &mut impl Write,
+    config: &Config,
+    count: u64,
+    line: &[u8],
+) -> MyResult<()> {
+    let text = line.strip_suffix(&[config.delimiter]).unwrap_or(line);
+    match config.count_format {
+        CountFormat::Padded => {
+            write!(out_file, "{:>4} ", count)?;
+            out_file.write_all(line)?;
+        }
+        CountFormat::Tsv => {
+            write!(out_file, "{}\t", count)?;
+            out_file.write_all(text)?;
+            out_file.write_all(&[config.delimiter])?;
+        }
+        CountFormat::Json => writeln!(
+            out_file,
+            r#"{{"count":{},"line":{}}}"#,
+            count,
+            json_string(&String::from_utf8_lossy(text))
+        )?,
+    }
+    Ok(())
+}
+
+// Quote a string for JSON output.
+// Copied from wcr/src/main.rs, fix both together
+fn json_string(value: &str) -> String {
+    let mut quoted = String::from("\"");
+    for ch in value.chars() {
+        match ch {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            ch if ch.is_control() => quoted.push_str(&format!("\\u{:04x}", ch as u32)),
+            ch => quoted.push(ch),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
 fn open(filename: &str) -> MyResult<Box<dyn BufRead>> {
     match filename {
         "-" => Ok(Box::new(BufReader::new(io::stdin()))),
         _ => Ok(Box::new(BufReader::new(File::open(filename)?))),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{Config, CountFormat, comparison_key, json_string, parse_size};
+
+    fn config() -> Config {
+        Config {
+            in_file: "-".to_string(),
+            out_file: None,
+            count: false,
+            repeated: false,
+            unique: false,
+            all_repeated: None,
+            group: None,
+            ignore_case: false,
+            skip_fields: 0,
+            skip_chars: 0,
+            check_chars: None,
+            global: false,
+            max_memory: None,
+            delimiter: b'\n',
+            count_format: CountFormat::Padded,
+            top: None,
+        }
+    }
+
+    #[test]
+    fn test_comparison_key() {
+        assert_eq!(comparison_key(b"abc\n", &config()), b"abc");
+        // Trailing blanks and carriage returns are part of the line
+        assert_eq!(comparison_key(b"abc \r\n", &config()), b"abc \r");
+        assert_eq!(comparison_key(b"\xffabc", &config()), b"\xffabc");
+
+        let zero = Config {
+            delimiter: b'\0',
+            ..config()
+        };
+        assert_eq!(comparison_key(b"a\nb\0", &zero), b"a\nb");
+
+        let skip_fields = Config {
+            skip_fields: 2,
+            ..config()
+        };
+        assert_eq!(
+            comparison_key(b"12:00:01  INFO disk full\n", &skip_fields),
+            b" disk full"
+        );
+        // Running out of fields leaves nothing to compare
+        assert_eq!(comparison_key(b"one\n", &skip_fields), b"");
+
+        let skip_chars = Config {
+            skip_fields: 1,
+            skip_chars: 2,
+            check_chars: Some(3),
+            ..config()
+        };
+        assert_eq!(comparison_key(b"x  abcdef\n", &skip_chars), b"abc");
+
+        let ignore_case = Config {
+            ignore_case: true,
+            ..config()
+        };
+        assert_eq!(
+            comparison_key("ÄbC\n".as_bytes(), &ignore_case),
+            "äbc".as_bytes()
+        );
+        // Invalid bytes are neither lowercased nor merged into one U+FFFD
+        assert_eq!(comparison_key(b"A\xff\n", &ignore_case), b"a\xff");
+        assert_ne!(
+            comparison_key(b"a\xff\n", &ignore_case),
+            comparison_key(b"a\xfe\n", &ignore_case)
+        );
+        // Each invalid byte is one character
+        assert_eq!(comparison_key(b"x \xff\xfeabcd\n", &skip_chars), b"\xfeab");
+        assert_eq!(
+            comparison_key(b"x \xc3\xa9\xff\xc3\xa9\xfez\n", &skip_chars),
+            b"\xff\xc3\xa9\xfe"
+        );
+    }
+
+    #[test]
+    fn test_parse_size() {
+        assert_eq!(parse_size("100").unwrap(), 100);
+        assert_eq!(parse_size("4K").unwrap(), 4096);
+        assert_eq!(parse_size("2M").unwrap(), 2 << 20);
+        assert_eq!(parse_size("1G").unwrap(), 1 << 30);
+        assert_eq!(
+            parse_size("1X").unwrap_err().to_string(),
+            "invalid memory size -- '1X'"
+        );
+        assert!(parse_size("M").is_err());
+    }
+
+    #[test]
+    fn test_json_string() {
+        assert_eq!(json_string("apple"), r#""apple""#);
+        assert_eq!(json_string("a \"b\"\\c\t"), r#""a \"b\"\\c\t""#);
+        assert_eq!(json_string("\x1b"), r#""\u001b""#);
+    }
+}
@@ -0,0 +1,305 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, ErrorKind, Read, Seek, SeekFrom, Write};
+use std::os::unix::fs::FileExt;
+
+// Rough heap cost of one entry in the in-memory map
+const ENTRY_COST: usize = 32;
+// A spilled entry is a hash followed by its count
+const RECORD_SIZE: u64 = 16;
+// Records per page of a spilled file, a lookup reads a single page
+const PAGE_RECORDS: u64 = 64;
+
+// Remembers which lines were seen and how often, by their hash.
+// Once spilled, entries live in files sorted by hash, which are searched
+// and have their counts updated in place. Runs of similar size are merged,
+// so there are only about log n of them.
+#[derive(Debug, Default)]
+pub struct Seen {
+    memory: HashMap<u64, u64>,
+    // From oldest and biggest to newest and smallest
+    runs: Vec<Run>,
+}
+
+#[derive(Debug)]
+struct Run {
+    file: File,
+    // Number of records
+    len: u64,
+    // First hash of every page, the only part of a run kept in memory
+    index: Vec<u64>,
+}
+
+impl Run {
+    // Write sorted records to a new file
+    fn write(entries: impl IntoIterator<Item = io::Result<(u64, u64)>>) -> io::Result<Self> {
+        let mut out = BufWriter::new(tempfile::tempfile()?);
+        let mut len = 0;
+        let mut index = Vec::new();
+        for entry in entries {
+            let (hash, count) = entry?;
+            if len % PAGE_RECORDS == 0 {
+                index.push(hash);
+            }
+            out.write_all(&hash.to_le_bytes())?;
+            out.write_all(&count.to_le_bytes())?;
+            len += 1;
+        }
+        let file = out.into_inner().map_err(|e| e.into_error())?;
+        Ok(Self { file, len, index })
+    }
+
+    // Read all records in order
+    fn records(&self) -> io::Result<impl Iterator<Item = io::Result<(u64, u64)>> + '_> {
+        let mut reader = BufReader::new(&self.file);
+        reader.seek(SeekFrom::Start(0))?;
+        let mut buf = [0; RECORD_SIZE as usize];
+        Ok((0..self.len).map(move |_| {
+            reader.read_exact(&mut buf)?;
+            Ok(split_record(&buf))
+        }))
+    }
+
+    // Combine two runs into one, the hashes in them never overlap
+    fn merge(&self, other: &Run) -> io::Result<Self> {
+        let mut left = self.records()?.peekable();
+        let mut right = other.records()?.peekable();
+        Run::write(std::iter::from_fn(|| {
+            let take_left = match (left.peek(), right.peek()) {
+                (Some(Ok((a, _))), Some(Ok((b, _)))) => a < b,
+                // Pass read errors on right away
+                (Some(Err(_)), _) => true,
+                (_, Some(Err(_))) => false,
+                (left, _) => left.is_some(),
+            };
+            if take_left { left.next() } else { right.next() }
+        }))
+    }
+
+    // Index and count of the record for a hash
+    fn find(&self, hash: u64) -> io::Result<Option<(u64, u64)>> {
+        let Some(page) = self
+            .index
+            .partition_point(|first| *first <= hash)
+            .checked_sub(1)
+        else {
+            return Ok(None);
+        };
+        let start = page as u64 * PAGE_RECORDS;
+        let records = PAGE_RECORDS.min(self.len - start);
+        let mut buf = vec![0; (records * RECORD_SIZE) as usize];
+        self.file.read_exact_at(&mut buf, start * RECORD_SIZE)?;
+        let page: Vec<_> = buf
+            .chunks_exact(RECORD_SIZE as usize)
+            .map(split_record)
+            .collect();
+        Ok(page
+            .binary_search_by_key(&hash, |(found, _)| *found)
+            .ok()
+            .map(|pos| (start + pos as u64, page[pos].1)))
+    }
+
+    fn set_count(&self, index: u64, count: u64) -> io::Result<()> {
+        self.file
+            .write_all_at(&count.to_le_bytes(), index * RECORD_SIZE + 8)
+    }
+}
+
+fn split_record(buf: &[u8]) -> (u64, u64) {
+    let (hash, count) = buf.split_at(8);
+    (
+        u64::from_le_bytes(hash.try_into().unwrap()),
+        u64::from_le_bytes(count.try_into().unwrap()),
+    )
+}
+
+impl Seen {
+    // Count one more occurrence, returns whether it is the first one
+    pub fn insert(&mut self, hash: u64) -> io::Result<bool> {
+        if let Some(count) = self.memory.get_mut(&hash) {
+            *count += 1;
+            return Ok(false);
+        }
+        for run in &self.runs {
+            if let Some((index, count)) = run.find(hash)? {
+                run.set_count(index, count + 1)?;
+                return Ok(false);
+            }
+        }
+        self.memory.insert(hash, 1);
+        Ok(true)
+    }
+
+    pub fn count(&self, hash: u64) -> io::Result<u64> {
+        if let Some(count) = self.memory.get(&hash) {
+            return Ok(*count);
+        }
+        for run in &self.runs {
+            if let Some((_, count)) = run.find(hash)? {
+                return Ok(count);
+            }
+        }
+        Ok(0)
+    }
+
+    pub fn memory_used(&self) -> usize {
+        self.memory.len() * ENTRY_COST
+    }
+
+    // Move everything held in memory to a new sorted file,
+    // then merge runs until each is at least twice the size of the next
+    pub fn spill(&mut self) -> io::Result<()> {
+        if self.memory.is_empty() {
+            return Ok(());
+        }
+        let mut entries: Vec<_> = self.memory.drain().collect();
+        entries.sort_unstable();
+        self.runs.push(Run::write(entries.into_iter().map(Ok))?);
+
+        while let [.., older, newer] = &self.runs[..]
+            && older.len <= 2 * newer.len
+        {
+            let merged = older.merge(newer)?;
+            self.runs.truncate(self.runs.len() - 2);
+            self.runs.push(merged);
+        }
+        Ok(())
+    }
+}
+
+// Lines in the order they were first seen, kept for `--global -c`
+// because the counts are only known at the end
+#[derive(Debug, Default)]
+pub struct FirstSeen {
+    lines: Vec<(u64, Vec<u8>)>,
+    bytes: usize,
+    // Lines spilled so far, they all come before the ones in memory
+    disk: Option<BufWriter<File>>,
+}
+
+impl FirstSeen {
+    pub fn push(&mut self, hash: u64, line: Vec<u8>) {
+        self.bytes += line.len() + size_of::<(u64, Vec<u8>)>();
+        self.lines.push((hash, line));
+    }
+
+    pub fn memory_used(&self) -> usize {
+        self.bytes
+    }
+
+    pub fn spill(&mut self) -> io::Result<()> {
+        let disk = match &mut self.disk {
+            Some(disk) => disk,
+            None => self.disk.insert(BufWriter::new(tempfile::tempfile()?)),
+        };
+        for (hash, line) in self.lines.drain(..) {
+            disk.write_all(&hash.to_le_bytes())?;
+            disk.write_all(&(line.len() as u64).to_le_bytes())?;
+            disk.write_all(&line)?;
+        }
+        self.bytes = 0;
+        Ok(())
+    }
+
+    // Go through the lines in first-seen order
+    pub fn for_each<E: From<io::Error>>(
+        self,
+        mut f: impl FnMut(u64, &[u8]) -> Result<(), E>,
+    ) -> Result<(), E> {
+        if let Some(disk) = self.disk {
+            let mut file = disk.into_inner().map_err(|e| e.into_error())?;
+            file.seek(SeekFrom::Start(0))?;
+            let mut reader = BufReader::new(file);
+            let mut buf = [0; 8];
+            let mut line = Vec::new();
+            loop {
+                match reader.read_exact(&mut buf) {
+                    Err(e) if e.kind() == ErrorKind::UnexpectedEof => break,
+                    res => res?,
+                }
+                let hash = u64::from_le_bytes(buf);
+                reader.read_exact(&mut buf)?;
+                line.resize(u64::from_le_bytes(buf) as usize, 0);
+                reader.read_exact(&mut line)?;
+                f(hash, &line)?;
+            }
+        }
+        for (hash, line) in &self.lines {
+            f(*hash, line)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{FirstSeen, Seen};
+
+    #[test]
+    fn test_seen_spill() {
+        let mut seen = Seen::default();
+        assert!(seen.insert(3).unwrap());
+        assert!(seen.insert(1).unwrap());
+        assert!(!seen.insert(3).unwrap());
+        seen.spill().unwrap();
+        assert_eq!(seen.memory_used(), 0);
+
+        // Found and updated on disk
+        assert!(!seen.insert(3).unwrap());
+        assert!(seen.insert(2).unwrap());
+        seen.spill().unwrap();
+        assert!(!seen.insert(2).unwrap());
+        assert_eq!(seen.count(1).unwrap(), 1);
+        assert_eq!(seen.count(2).unwrap(), 2);
+        assert_eq!(seen.count(3).unwrap(), 3);
+        assert_eq!(seen.count(4).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_seen_many_spills() {
+        let mut seen = Seen::default();
+        let hashes = (0..5000u64).map(|n| n.wrapping_mul(0x9e37_79b9_7f4a_7c15));
+        for (n, hash) in hashes.clone().enumerate() {
+            assert!(seen.insert(hash).unwrap());
+            if n % 10 == 9 {
+                seen.spill().unwrap();
+            }
+        }
+        // 500 spills are merged down to a handful of runs
+        assert!(seen.runs.len() <= 12, "{} runs", seen.runs.len());
+
+        for hash in hashes.clone() {
+            assert!(!seen.insert(hash).unwrap());
+        }
+        for hash in hashes {
+            assert_eq!(seen.count(hash).unwrap(), 2);
+        }
+        assert_eq!(seen.count(1).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_first_seen_order() {
+        let mut firsts = FirstSeen::default();
+        firsts.push(1, b"a\n".to_vec());
+        firsts.spill().unwrap();
+        firsts.push(2, b"b\n".to_vec());
+        firsts.spill().unwrap();
+        firsts.push(3, b"c".to_vec());
+
+        let mut lines = vec![];
+        firsts
+            .for_each(|hash, line| -> std::io::Result<()> {
+                lines.push((hash, line.to_vec()));
+                Ok(())
+            })
+            .unwrap();
+        assert_eq!(
+            lines,
+            [
+                (1, b"a\n".to_vec()),
+                (2, b"b\n".to_vec()),
+                (3, b"c".to_vec())
+            ]
+        );
+    }
+}
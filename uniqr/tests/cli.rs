@@ -80,6 +80,14 @@ const T6: Test = Test {
 };
 
 // --------------------------------------------------
+// Log lines that differ by timestamp and case
+const LOG: &str = "tests/inputs/log.txt";
+
+// Lines that only differ in trailing bytes, some not valid UTF-8
+const EXACT: &str = "tests/inputs/exact.txt";
+// NUL-terminated records with newlines inside
+const ZERO: &str = "tests/inputs/zero.txt";
+
 fn gen_bad_file() -> String {
     loop {
         let filename: String = rand::thread_rng()
@@ -616,3 +624,268 @@ fn t6_outfile_count() -> Result<()> {
 fn t6_stdin_outfile_count() -> Result<()> {
     run_stdin_outfile_count(&T6)
 }
+
+// --------------------------------------------------
+fn run_args(args: &[&str], expected_file: &str) -> Result<()> {
+    let expected = fs::read_to_string(expected_file)?;
+    let output = cargo::cargo_bin_cmd!("uniqr")
+        .args(args)
+        .output()
+        .expect("fail");
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8(output.stdout).expect("invalid UTF-8");
+    assert_eq!(stdout, expected);
+    Ok(())
+}
+
+#[test]
+fn three_repeated() -> Result<()> {
+    run_args(&["-d", THREE.input], "tests/expected/three.txt.d.out")
+}
+
+#[test]
+fn three_unique() -> Result<()> {
+    run_args(&["-u", THREE.input], "tests/expected/three.txt.u.out")
+}
+
+#[test]
+fn three_all_repeated() -> Result<()> {
+    run_args(&["-D", THREE.input], "tests/expected/three.txt.D.out")
+}
+
+#[test]
+fn log_skip_fields() -> Result<()> {
+    run_args(&["-f", "1", LOG], "tests/expected/log.txt.f1.out")
+}
+
+#[test]
+fn log_skip_fields_ignore_case_count() -> Result<()> {
+    run_args(
+        &["-f1", "-i", "-c", LOG],
+        "tests/expected/log.txt.f1.i.c.out",
+    )
+}
+
+#[test]
+fn log_skip_fields_ignore_case_all_repeated() -> Result<()> {
+    run_args(
+        &["--skip-fields=1", "--ignore-case", "-D", LOG],
+        "tests/expected/log.txt.f1.i.D.out",
+    )
+}
+
+#[test]
+fn log_skip_chars_check_chars() -> Result<()> {
+    run_args(
+        &["-f", "2", "-s", "1", "-w", "4", "-u", LOG],
+        "tests/expected/log.txt.f2.s1.w4.u.out",
+    )
+}
+
+#[test]
+fn dies_bad_skip_fields() -> Result<()> {
+    cargo::cargo_bin_cmd!("uniqr")
+        .args(["-f", "x", LOG])
+        .assert()
+        .failure()
+        .stderr("invalid number of fields to skip -- 'x'\n");
+    Ok(())
+}
+
+#[test]
+fn dies_all_repeated_and_count() -> Result<()> {
+    cargo::cargo_bin_cmd!("uniqr")
+        .args(["-D", "-c", LOG])
+        .assert()
+        .failure();
+    Ok(())
+}
+
+#[test]
+fn three_global() -> Result<()> {
+    run_args(
+        &["--global", THREE.input],
+        "tests/expected/three.txt.global.out",
+    )
+}
+
+#[test]
+fn three_global_count() -> Result<()> {
+    run_args(
+        &["--global", "-c", THREE.input],
+        "tests/expected/three.txt.global.c.out",
+    )
+}
+
+#[test]
+fn three_global_count_spilled() -> Result<()> {
+    // Every line goes over the limit, so everything ends up on disk
+    run_args(
+        &["--global", "-c", "--max-memory", "1", THREE.input],
+        "tests/expected/three.txt.global.c.out",
+    )
+}
+
+#[test]
+fn log_global_skip_fields_ignore_case() -> Result<()> {
+    run_args(
+        &["--global", "-f1", "-i", LOG],
+        "tests/expected/log.txt.global.f1.i.out",
+    )
+}
+
+#[test]
+fn dies_max_memory_without_global() -> Result<()> {
+    cargo::cargo_bin_cmd!("uniqr")
+        .args(["--max-memory", "1M", LOG])
+        .assert()
+        .failure();
+    Ok(())
+}
+
+#[test]
+fn three_group() -> Result<()> {
+    run_args(
+        &["--group", THREE.input],
+        "tests/expected/three.txt.group.separate.out",
+    )
+}
+
+#[test]
+fn three_group_methods() -> Result<()> {
+    for method in ["separate", "prepend", "append", "both"] {
+        run_args(
+            &[&format!("--group={method}"), THREE.input],
+            &format!("tests/expected/three.txt.group.{method}.out"),
+        )?;
+    }
+    Ok(())
+}
+
+#[test]
+fn three_all_repeated_methods() -> Result<()> {
+    run_args(
+        &["--all-repeated=none", THREE.input],
+        "tests/expected/three.txt.D.out",
+    )?;
+    for method in ["prepend", "separate"] {
+        run_args(
+            &[&format!("--all-repeated={method}"), THREE.input],
+            &format!("tests/expected/three.txt.D.{method}.out"),
+        )?;
+    }
+    Ok(())
+}
+
+#[test]
+fn empty_group_both() -> Result<()> {
+    run_args(&["--group=both", EMPTY.input], EMPTY.out)
+}
+
+#[test]
+fn dies_group_and_count() -> Result<()> {
+    cargo::cargo_bin_cmd!("uniqr")
+        .args(["--group", "-c", THREE.input])
+        .assert()
+        .failure();
+    Ok(())
+}
+
+fn run_bytes(args: &[&str], expected_file: &str) -> Result<()> {
+    let expected = fs::read(expected_file)?;
+    let output = cargo::cargo_bin_cmd!("uniqr")
+        .args(args)
+        .output()
+        .expect("fail");
+    assert!(output.status.success());
+    assert_eq!(output.stdout, expected);
+    Ok(())
+}
+
+#[test]
+fn exact_lines() -> Result<()> {
+    // Trailing blanks, carriage returns and invalid UTF-8 all count
+    run_bytes(&[EXACT], "tests/expected/exact.txt.out")
+}
+
+#[test]
+fn exact_lines_count() -> Result<()> {
+    run_bytes(&["-c", EXACT], "tests/expected/exact.txt.c.out")
+}
+
+#[test]
+fn zero_terminated_count() -> Result<()> {
+    run_bytes(&["-z", "-c", ZERO], "tests/expected/zero.txt.z.c.out")
+}
+
+#[test]
+fn zero_terminated_global() -> Result<()> {
+    run_bytes(
+        &["--zero-terminated", "--global", ZERO],
+        "tests/expected/zero.txt.z.global.out",
+    )
+}
+
+#[test]
+fn three_top() -> Result<()> {
+    run_args(
+        &["--top", "2", THREE.input],
+        "tests/expected/three.txt.top2.out",
+    )
+}
+
+#[test]
+fn three_top_tsv() -> Result<()> {
+    // Asking for more lines than there are prints them all
+    run_args(
+        &["--top", "10", "--count-format", "tsv", THREE.input],
+        "tests/expected/three.txt.top10.tsv.out",
+    )
+}
+
+#[test]
+fn three_count_json() -> Result<()> {
+    run_args(
+        &["-c", "--count-format=json", THREE.input],
+        "tests/expected/three.txt.c.json.out",
+    )
+}
+
+#[test]
+fn log_top_skip_fields_ignore_case_json() -> Result<()> {
+    run_args(
+        &["--top=2", "-f1", "-i", "--count-format=json", LOG],
+        "tests/expected/log.txt.top2.f1.i.json.out",
+    )
+}
+
+#[test]
+fn dies_bad_top() -> Result<()> {
+    cargo::cargo_bin_cmd!("uniqr")
+        .args(["--top", "many", LOG])
+        .assert()
+        .failure()
+        .stderr("invalid number of lines to report -- 'many'\n");
+    Ok(())
+}
+
+#[test]
+fn global_count_many_spills() -> Result<()> {
+    // 3000 unique lines, each twice, spilling every few lines
+    let input: String = (0..2)
+        .flat_map(|_| (0..3000).map(|n| format!("line {n}\n")))
+        .collect();
+    let expected: String = (0..3000).map(|n| format!("   2 line {n}\n")).collect();
+
+    let start = std::time::Instant::now();
+    cargo::cargo_bin_cmd!("uniqr")
+        .args(["--global", "-c", "--max-memory", "1K"])
+        .write_stdin(input)
+        .assert()
+        .success()
+        .stdout(expected);
+    // Merging the spilled runs keeps lookups cheap
+    assert!(start.elapsed() < std::time::Duration::from_secs(10));
+    Ok(())
+}
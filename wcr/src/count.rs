@@ -0,0 +1,478 @@
+use std::fs::File;
+use std::io::{self, ErrorKind, Read};
+use std::os::unix::fs::FileExt;
+use std::thread;
+use unicode_width::UnicodeWidthChar;
+
+// How much is read from a file at a time
+const CHUNK_SIZE: usize = 64 * 1024;
+// Regular files at least this big are split across threads
+pub const SPLIT_THRESHOLD: u64 = 16 * 1024 * 1024;
+
+// Explicitly declare that the following data structure must implement these traits
+#[derive(Debug, Default, PartialEq)]
+pub struct FileInfo {
+    // usize is more dynamic than other unsigned integer types?
+    pub num_lines: usize,
+    pub num_words: usize,
+    pub num_bytes: usize,
+    pub num_chars: usize,
+    // Display width of the longest line
+    pub max_line_length: usize,
+}
+
+impl FileInfo {
+    // Fold the counts of another file into a running total
+    pub fn add(&mut self, other: &FileInfo) {
+        self.num_lines += other.num_lines;
+        self.num_words += other.num_words;
+        self.num_bytes += other.num_bytes;
+        self.num_chars += other.num_chars;
+        // The total of -L is the longest line of all files, not a sum
+        self.max_line_length = self.max_line_length.max(other.max_line_length);
+    }
+}
+
+fn is_space(byte: u8) -> bool {
+    matches!(byte, b' ' | b'\t' | b'\n' | b'\x0b' | b'\x0c' | b'\r')
+}
+
+// What needs counting besides lines, words and bytes
+#[derive(Debug, Default, Clone, Copy)]
+pub struct CountOptions {
+    pub chars: bool,
+    pub max_line_length: bool,
+    // Only ASCII whitespace separates words, as in the C locale
+    pub ascii_words: bool,
+}
+
+// Counts a stream of bytes handed to it one chunk at a time,
+// so chunks can end anywhere, even in the middle of a word or character
+#[derive(Debug, Default)]
+pub struct Counter {
+    info: FileInfo,
+    // Whether any character was seen yet
+    started: bool,
+    // Whether the first character seen belonged to a word
+    starts_in_word: bool,
+    // Whether the last character seen belonged to a word
+    in_word: bool,
+    // Plain ASCII is never decoded, and characters are only counted when asked
+    count_chars: bool,
+    measure_width: bool,
+    ascii_words: bool,
+    // Display column the current line has reached
+    line_pos: usize,
+    // Start of a multi-byte character cut off at the end of the last chunk
+    partial: Vec<u8>,
+}
+
+impl Counter {
+    pub fn new(options: CountOptions) -> Self {
+        Self {
+            count_chars: options.chars || options.max_line_length,
+            measure_width: options.max_line_length,
+            ascii_words: options.ascii_words,
+            ..Self::default()
+        }
+    }
+
+    pub fn update(&mut self, chunk: &[u8]) {
+        self.info.num_bytes += chunk.len();
+        // Simple enough for the compiler to vectorize
+        self.info.num_lines += chunk.iter().filter(|byte| **byte == b'\n').count();
+
+        if self.ascii_words && !self.count_chars {
+            // Nothing needs the characters
+            self.words(chunk.iter().map(|byte| is_space(*byte)));
+        } else if self.partial.is_empty() && chunk.is_ascii() {
+            // SAFETY: ASCII is valid UTF-8
+            self.valid(unsafe { std::str::from_utf8_unchecked(chunk) });
+        } else {
+            self.decode(chunk);
+        }
+    }
+
+    // A word starts wherever a non-space follows a space
+    fn words(&mut self, spaces: impl IntoIterator<Item = bool>) {
+        let mut spaces = spaces.into_iter();
+        if !self.started {
+            let Some(space) = spaces.next() else {
+                return;
+            };
+            self.started = true;
+            self.starts_in_word = !space;
+            self.info.num_words += usize::from(!space);
+            self.in_word = !space;
+        }
+        let mut in_word = self.in_word;
+        for space in spaces {
+            self.info.num_words += usize::from(!space && !in_word);
+            in_word = !space;
+        }
+        self.in_word = in_word;
+    }
+
+    // Decode characters the way String::from_utf8_lossy would:
+    // every invalid sequence turns into a single U+FFFD
+    fn decode(&mut self, chunk: &[u8]) {
+        let mut buf = std::mem::take(&mut self.partial);
+        let mut rest = if buf.is_empty() {
+            chunk
+        } else {
+            buf.extend_from_slice(chunk);
+            &buf[..]
+        };
+        loop {
+            let err = match std::str::from_utf8(rest) {
+                Ok(text) => {
+                    self.valid(text);
+                    return;
+                }
+                Err(err) => err,
+            };
+            let (valid, after) = rest.split_at(err.valid_up_to());
+            // SAFETY: from_utf8 just checked everything up to valid_up_to
+            self.valid(unsafe { std::str::from_utf8_unchecked(valid) });
+            match err.error_len() {
+                Some(len) => {
+                    self.invalid();
+                    rest = &after[len..];
+                }
+                None => {
+                    // Still waiting for the rest of the character
+                    self.partial = after.to_vec();
+                    return;
+                }
+            }
+        }
+    }
+
+    fn valid(&mut self, text: &str) {
+        if text.is_ascii() {
+            self.words(text.bytes().map(is_space));
+            if self.count_chars {
+                self.info.num_chars += text.len();
+            }
+        } else {
+            if self.ascii_words {
+                self.words(text.bytes().map(is_space));
+            } else {
+                // Unicode has spaces of its own, such as U+00A0 and U+3000
+                self.words(text.chars().map(char::is_whitespace));
+            }
+            if self.count_chars {
+                // Every character has exactly one byte that is not a continuation byte
+                self.info.num_chars += text.bytes().filter(|byte| *byte & 0xc0 != 0x80).count();
+            }
+        }
+        if self.measure_width {
+            self.measure(text);
+        }
+    }
+
+    // Track display columns the way GNU wc -L does:
+    // wide characters take two columns, tabs advance to the next multiple of 8
+    // and control characters take none
+    fn measure(&mut self, text: &str) {
+        for ch in text.chars() {
+            match ch {
+                '\t' => self.line_pos += 8 - self.line_pos % 8,
+                // Carriage returns and form feeds move back to the first column
+                '\n' | '\r' | '\x0c' => self.end_line(),
+                _ => self.line_pos += ch.width().unwrap_or(0),
+            }
+        }
+    }
+
+    // Not a valid sequence, it is one character that takes no columns
+    // and belongs to a word
+    fn invalid(&mut self) {
+        if self.count_chars {
+            self.info.num_chars += 1;
+        }
+        self.words([false]);
+    }
+
+    // A character cut off by the end of the input still counts, as U+FFFD
+    fn flush_partial(&mut self) {
+        if !self.partial.is_empty() {
+            self.invalid();
+            self.partial.clear();
+        }
+    }
+
+    fn end_line(&mut self) {
+        self.info.max_line_length = self.info.max_line_length.max(self.line_pos);
+        self.line_pos = 0;
+    }
+
+    // Add the counts of the bytes that directly follow the ones seen so far,
+    // counted separately by another thread
+    pub fn append(&mut self, mut next: Counter) {
+        if next.info.num_bytes == 0 {
+            return;
+        }
+        // Parts start on a character boundary, so whatever is left over is invalid
+        next.flush_partial();
+        if self.info.num_bytes == 0 {
+            *self = next;
+            return;
+        }
+        self.flush_partial();
+        // A word cut in two at the edge was counted on both sides
+        if self.in_word && next.starts_in_word {
+            self.info.num_words -= 1;
+        }
+        self.in_word = next.in_word;
+        self.info.add(&next.info);
+    }
+
+    pub fn finish(mut self) -> FileInfo {
+        self.flush_partial();
+        self.end_line();
+        self.info
+    }
+}
+
+// Count everything read from a stream, chunk by chunk
+pub fn count(mut file: impl Read, options: CountOptions) -> io::Result<FileInfo> {
+    let mut counter = Counter::new(options);
+    let mut buf = vec![0; CHUNK_SIZE];
+    loop {
+        match file.read(&mut buf) {
+            Ok(0) => break,
+            Ok(bytes_read) => counter.update(&buf[..bytes_read]),
+            Err(err) if err.kind() == ErrorKind::Interrupted => {}
+            Err(err) => return Err(err),
+        }
+    }
+    Ok(counter.finish())
+}
+
+// Count a large regular file by giving each thread its own slice of it.
+// Line widths are not tracked, -L needs the file in order.
+pub fn count_split(
+    file: &File,
+    len: u64,
+    threads: usize,
+    options: CountOptions,
+) -> io::Result<FileInfo> {
+    let part_len = len.div_ceil(threads as u64);
+    let parts = thread::scope(|s| {
+        let bounds = (0..=threads as u64)
+            .map(|part| char_boundary(file, (part * part_len).min(len), len))
+            .collect::<io::Result<Vec<_>>>()?;
+        let handles: Vec<_> = bounds
+            .windows(2)
+            .map(|range| {
+                let (start, end) = (range[0], range[1]);
+                s.spawn(move || count_range(file, start, end, options))
+            })
+            .collect();
+        handles
+            .into_iter()
+            .map(|handle| handle.join().expect("counting thread panicked"))
+            .collect::<io::Result<Vec<_>>>()
+    })?;
+
+    let mut counter = Counter::new(options);
+    for part in parts {
+        counter.append(part);
+    }
+    Ok(counter.finish())
+}
+
+// Move an offset forward past continuation bytes, so no character is cut in two.
+// A valid character has at most three of them, any further ones are invalid
+// on their own and decode the same on either side.
+fn char_boundary(file: &File, pos: u64, len: u64) -> io::Result<u64> {
+    let mut buf = [0; 3];
+    let bytes_read = file.read_at(&mut buf, pos)?;
+    let skip = buf[..bytes_read]
+        .iter()
+        .take_while(|byte| **byte & 0xc0 == 0x80)
+        .count();
+    Ok((pos + skip as u64).min(len))
+}
+
+fn count_range(file: &File, start: u64, end: u64, options: CountOptions) -> io::Result<Counter> {
+    let mut counter = Counter::new(options);
+    let mut buf = vec![0; CHUNK_SIZE];
+    let mut pos = start;
+    while pos < end {
+        let want = CHUNK_SIZE.min((end - pos) as usize);
+        let bytes_read = match file.read_at(&mut buf[..want], pos) {
+            // The file shrank while we were reading it
+            Ok(0) => break,
+            Ok(bytes_read) => bytes_read,
+            Err(err) if err.kind() == ErrorKind::Interrupted => continue,
+            Err(err) => return Err(err),
+        };
+        counter.update(&buf[..bytes_read]);
+        pos += bytes_read as u64;
+    }
+    Ok(counter)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CountOptions, Counter, FileInfo, count, count_split};
+    use std::fs::File;
+    // In-memory buffer to fake a filehandle for tests
+    // For production, use File::open
+    use std::io::Cursor;
+
+    const WORDS: CountOptions = CountOptions {
+        chars: false,
+        max_line_length: false,
+        ascii_words: false,
+    };
+    const CHARS: CountOptions = CountOptions {
+        chars: true,
+        ..WORDS
+    };
+    const ALL: CountOptions = CountOptions {
+        max_line_length: true,
+        ..CHARS
+    };
+
+    #[test]
+    fn test_count() {
+        let text = "I don't want the world. I just want your half.\r\n";
+        let info = count(Cursor::new(text), ALL);
+        assert!(info.is_ok());
+        let expected = FileInfo {
+            num_lines: 1,
+            num_words: 10,
+            num_chars: 48,
+            num_bytes: 48,
+            max_line_length: 46,
+        };
+        assert_eq!(info.unwrap(), expected);
+    }
+
+    #[test]
+    fn test_skip_chars() {
+        // Characters are only decoded when asked for
+        let info = count(Cursor::new("日本語 ok\n"), WORDS).unwrap();
+        assert_eq!(info.num_chars, 0);
+        assert_eq!(info.num_words, 2);
+        assert_eq!(info.num_bytes, 13);
+    }
+
+    fn max_line_length(text: &str) -> usize {
+        count(Cursor::new(text), ALL).unwrap().max_line_length
+    }
+
+    #[test]
+    fn test_max_line_length() {
+        assert_eq!(max_line_length(""), 0);
+        assert_eq!(max_line_length("hello\nhi\n"), 5);
+        assert_eq!(max_line_length("a\tb"), 9);
+        // CJK characters are two columns wide
+        assert_eq!(max_line_length("日本語\n"), 6);
+        assert_eq!(max_line_length("long line\rshort"), 9);
+    }
+
+    #[test]
+    fn test_split_anywhere() {
+        // Every split point must give the same counts as one pass,
+        // including in the middle of words and spaces.
+        // count_split never cuts a character in two.
+        let text = "Frétt hefir öld  óvu,\n þá er endr\tof gerðu\n";
+        let expected = count(Cursor::new(text), CHARS).unwrap();
+        for mid in (0..=text.len()).filter(|mid| text.is_char_boundary(*mid)) {
+            let (head, tail) = text.as_bytes().split_at(mid);
+            let mut counter = Counter::new(CHARS);
+            counter.update(head);
+            let mut rest = Counter::new(CHARS);
+            rest.update(tail);
+            counter.append(rest);
+            assert_eq!(counter.finish(), expected, "split at {}", mid);
+        }
+    }
+
+    #[test]
+    fn test_unicode_spaces() {
+        // No-break and ideographic spaces separate words too
+        let text = "a\u{a0}b c\u{3000}d\n";
+        assert_eq!(count(Cursor::new(text), WORDS).unwrap().num_words, 4);
+
+        // Fed one byte at a time, the spaces are cut in two
+        let mut counter = Counter::new(WORDS);
+        for byte in text.as_bytes() {
+            counter.update(&[*byte]);
+        }
+        assert_eq!(counter.finish().num_words, 4);
+
+        // A part starting with a wide space does not start in a word
+        let (head, tail) = text.split_at(text.find('\u{3000}').unwrap());
+        let mut counter = Counter::new(WORDS);
+        counter.update(head.as_bytes());
+        let mut rest = Counter::new(WORDS);
+        rest.update(tail.as_bytes());
+        counter.append(rest);
+        assert_eq!(counter.finish().num_words, 4);
+    }
+
+    #[test]
+    fn test_ascii_words() {
+        let ascii = CountOptions {
+            ascii_words: true,
+            ..WORDS
+        };
+        let text = "a\u{a0}b c\u{3000}d\n";
+        assert_eq!(count(Cursor::new(text), ascii).unwrap().num_words, 2);
+        let ascii_chars = CountOptions {
+            chars: true,
+            ..ascii
+        };
+        let info = count(Cursor::new(text), ascii_chars).unwrap();
+        assert_eq!((info.num_words, info.num_chars), (2, 8));
+    }
+
+    #[test]
+    fn test_width_across_chunks() {
+        let text = "日本語\n".as_bytes();
+        let mut counter = Counter::new(ALL);
+        for byte in text {
+            counter.update(&[*byte]);
+        }
+        assert_eq!(counter.finish().max_line_length, 6);
+    }
+
+    #[test]
+    fn test_count_split() {
+        let path = "tests/inputs/atlamal.txt";
+        let file = File::open(path).unwrap();
+        let len = file.metadata().unwrap().len();
+        let expected = count(File::open(path).unwrap(), CHARS).unwrap();
+        for threads in 1..=7 {
+            assert_eq!(count_split(&file, len, threads, CHARS).unwrap(), expected);
+        }
+    }
+
+    #[test]
+    fn test_invalid_utf8() {
+        // A lone byte, a truncated sequence and a stray continuation byte
+        // each count as one replacement character
+        let text = b"caf\xe9 \xe6\x97\n\x80ok\n\xe6\x97";
+        let expected = FileInfo {
+            num_lines: 2,
+            num_words: 4,
+            num_bytes: text.len(),
+            num_chars: String::from_utf8_lossy(text).chars().count(),
+            max_line_length: 4,
+        };
+        assert_eq!(expected.num_chars, 12);
+        assert_eq!(count(Cursor::new(text), ALL).unwrap(), expected);
+
+        // Fed one byte at a time
+        let mut counter = Counter::new(ALL);
+        for byte in text {
+            counter.update(&[*byte]);
+        }
+        assert_eq!(counter.finish(), expected);
+    }
+}
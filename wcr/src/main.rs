@@ -1,7 +1,15 @@
+mod count;
+
 use clap::{App, Arg};
+use count::{CountOptions, FileInfo, SPLIT_THRESHOLD, count, count_split};
+use std::collections::HashMap;
 use std::error::Error;
-use std::fs::File;
-use std::io::{self, BufRead, BufReader};
+use std::fs::{self, File};
+use std::io::{self, ErrorKind, Read};
+use std::os::fd::AsFd;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc;
+use std::thread;
 
 type MyResult<T> = Result<T, Box<dyn Error>>;
 
@@ -12,6 +20,21 @@ struct Config {
     words: bool,
     bytes: bool,
     chars: bool,
+    max_line_length: bool,
+    // Words are separated by ASCII whitespace only, not by Unicode spaces
+    ascii_words: bool,
+    // Where the file names came from with --files0-from
+    files0_from: Option<String>,
+    format: Format,
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+enum Format {
+    // Padded columns like GNU wc
+    Text,
+    // One JSON object per line
+    Json,
+    Csv,
 }
 
 fn main() {
@@ -24,9 +47,14 @@ fn main() {
     //    Err(e) => Err(e)
     //}
     //
-    if let Err(e) = get_args().and_then(run) {
-        eprintln!("{}", e);
-        std::process::exit(1);
+    match get_args().and_then(run) {
+        Ok(true) => {}
+        // A file could not be counted, the error was already printed
+        Ok(false) => std::process::exit(1),
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
     }
 }
 
@@ -64,6 +92,36 @@ fn get_args() -> MyResult<Config> {
                 .takes_value(false)
                 .conflicts_with("bytes"),
         )
+        .arg(
+            Arg::with_name("max_line_length")
+                .short("L")
+                .long("max-line-length")
+                .help("Show the display width of the longest line")
+                .takes_value(false),
+        )
+        .arg(
+            Arg::with_name("word_separators")
+                .long("word-separators")
+                .value_name("SET")
+                .help("Whitespace that separates words, ascii or all of unicode")
+                .possible_values(&["ascii", "unicode"])
+                .default_value("unicode"),
+        )
+        .arg(
+            Arg::with_name("files0_from")
+                .long("files0-from")
+                .value_name("F")
+                .help("Read NUL-separated file names from F, or stdin when F is -")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("format")
+                .long("format")
+                .value_name("FORMAT")
+                .help("Print a record with all counts per file and for the total")
+                .possible_values(&["text", "json", "csv"])
+                .default_value("text"),
+        )
         .arg(
             Arg::with_name("lines")
                 .short("l")
@@ -78,13 +136,17 @@ fn get_args() -> MyResult<Config> {
     let mut words = matches.is_present("words");
     let mut bytes = matches.is_present("bytes");
     let chars = matches.is_present("chars");
+    let max_line_length = matches.is_present("max_line_length");
 
     // Iterator::all() method expects a closure (anon func works as a higher order func)
     // The anon function captures values from its surrouding scope,
     // and in this case those values are the flags?
     // BTW, we are comparing REFERENCES, not VALUES.
-    if [lines, words, bytes, chars].iter().all(|v| v == &false) {
-        // If all four flags are false
+    if [lines, words, bytes, chars, max_line_length]
+        .iter()
+        .all(|v| v == &false)
+    {
+        // If no flag is set
         // set some of them to true
         // to handle the case where no flag is used
         lines = true;
@@ -94,147 +156,329 @@ fn get_args() -> MyResult<Config> {
         // since setting it will conflict with --bytes
     }
 
+    let files0_from = matches.value_of("files0_from").map(String::from);
+    let files = match &files0_from {
+        // The default "-" does not count as an operand
+        Some(_) if matches.occurrences_of("files") > 0 => {
+            return Err(From::from(format!(
+                "extra operand '{}'\nfile operands cannot be combined with --files0-from",
+                matches.value_of("files").unwrap()
+            )));
+        }
+        Some(list) => read_file_list(list)?,
+        None => matches.values_of_lossy("files").unwrap(),
+    };
+
     Ok(Config {
-        files: matches.values_of_lossy("files").unwrap(),
+        files,
         lines,
         words,
         bytes,
         chars,
+        max_line_length,
+        ascii_words: matches.value_of("word_separators") == Some("ascii"),
+        files0_from,
+        format: match matches.value_of("format") {
+            Some("json") => Format::Json,
+            Some("csv") => Format::Csv,
+            _ => Format::Text,
+        },
     })
 }
 
-fn run(config: Config) -> MyResult<()> {
-    let mut total_lines = 0;
-    let mut total_words = 0;
-    let mut total_bytes = 0;
-    let mut total_chars = 0;
+// Read the NUL-separated file names given to --files0-from
+fn read_file_list(list: &str) -> MyResult<Vec<String>> {
+    let mut names = Vec::new();
+    match list {
+        "-" => io::stdin().read_to_end(&mut names),
+        _ => File::open(list).and_then(|mut file| file.read_to_end(&mut names)),
+    }
+    .map_err(|e| format!("cannot open '{}' for reading: {}", list, e))?;
+
+    // The last name may or may not be terminated
+    if names.last() == Some(&0) {
+        names.pop();
+    }
+    if names.is_empty() {
+        return Ok(vec![]);
+    }
+    Ok(names
+        .split(|byte| *byte == 0)
+        .map(|name| String::from_utf8_lossy(name).into_owned())
+        .collect())
+}
+
+// Returns Ok(false) when some file could not be opened or read.
+fn run(config: Config) -> MyResult<bool> {
+    let threads = thread::available_parallelism().map_or(1, |n| n.get());
+    // A lone file gets all the threads to itself, otherwise each file gets one
+    let split_threads = if config.files.len() == 1 { threads } else { 1 };
+    let next_file = AtomicUsize::new(0);
+    let (tx, rx) = mpsc::channel();
+    let mut total = FileInfo::default();
+    let mut all_ok = true;
+    let width = column_width(&config);
+    if config.format == Format::Csv {
+        println!("file,lines,words,chars,bytes");
+    }
 
-    for filename in &config.files {
-        match open(filename) {
-            Err(err) => eprintln!("{}: {}", filename, err),
-            Ok(file) => {
-                if let Ok(info) = count(file) {
-                    println!(
-                        "{}{}{}{}{}",
-                        format_field(info.num_lines, config.lines),
-                        format_field(info.num_words, config.words),
-                        format_field(info.num_chars, config.chars),
-                        format_field(info.num_bytes, config.bytes),
-                        if filename == "-" {
-                            // Stdin
-                            "".to_string()
-                        } else {
-                            format!(" {}", filename)
-                        }
-                    );
-
-                    total_lines += info.num_lines;
-                    total_words += info.num_words;
-                    total_bytes += info.num_bytes;
-                    total_chars += info.num_chars;
+    thread::scope(|s| {
+        for _ in 0..threads.min(config.files.len()) {
+            let tx = tx.clone();
+            let (config, next_file) = (&config, &next_file);
+            s.spawn(move || {
+                loop {
+                    let file_num = next_file.fetch_add(1, Ordering::Relaxed);
+                    let Some(filename) = config.files.get(file_num) else {
+                        break;
+                    };
+                    let result = count_file(filename, config, split_threads);
+                    if tx.send((file_num, result)).is_err() {
+                        break;
+                    }
+                }
+            });
+        }
+        drop(tx);
+
+        // Results come in whenever a file is done,
+        // hold them back until every file before them has been printed
+        let mut done = HashMap::new();
+        let mut next_print = 0;
+        for (file_num, result) in rx {
+            done.insert(file_num, result);
+            while let Some(result) = done.remove(&next_print) {
+                let filename = &config.files[next_print];
+                match result {
+                    Err(err) => {
+                        eprintln!("{}: {}", filename, err);
+                        all_ok = false;
+                    }
+                    Ok(info) => {
+                        print_record(Some(filename), &info, &config, width);
+                        total.add(&info);
+                    }
                 }
+                next_print += 1;
             }
         }
-    }
+    });
 
-    if config.files.len() > 1 {
-        println!(
-            "{}{}{}{} total",
-            format_field(total_lines, config.lines),
-            format_field(total_words, config.words),
-            format_field(total_bytes, config.bytes),
-            format_field(total_chars, config.chars)
-        );
+    // Records always end with the total so consumers can rely on it
+    if config.files.len() > 1 || config.format != Format::Text {
+        print_record(None, &total, &config, width);
     }
 
-    Ok(())
+    Ok(all_ok)
 }
 
-fn open(filename: &str) -> MyResult<Box<dyn BufRead>> {
-    match filename {
-        // Read from stdin
-        "-" => Ok(Box::new(BufReader::new(io::stdin()))),
-        // Open a physical file
-        _ => Ok(Box::new(BufReader::new(File::open(filename)?))),
+fn count_file(filename: &str, config: &Config, threads: usize) -> io::Result<FileInfo> {
+    if filename.is_empty() {
+        return Err(io::Error::new(
+            ErrorKind::InvalidInput,
+            "invalid zero-length file name",
+        ));
+    }
+    let options = CountOptions {
+        // CSV and JSON always have a chars field
+        chars: config.chars || config.format != Format::Text,
+        max_line_length: config.max_line_length,
+        ascii_words: config.ascii_words,
+    };
+    if filename == "-" {
+        // The names were read from stdin, there is nothing left to count
+        if config.files0_from.as_deref() == Some("-") {
+            return Err(io::Error::new(
+                ErrorKind::InvalidInput,
+                "file name '-' not allowed when reading file names from standard input",
+            ));
+        }
+        return count(io::stdin().lock(), options);
+    }
+    let file = File::open(filename)?;
+    let metadata = file.metadata()?;
+    if threads > 1
+        && !config.max_line_length
+        && metadata.is_file()
+        && metadata.len() >= SPLIT_THRESHOLD
+    {
+        count_split(&file, metadata.len(), threads, options)
+    } else {
+        count(file, options)
     }
 }
 
-// Explicitly declare that the following data structure must implement these traits
-#[derive(Debug, PartialEq)]
-struct FileInfo {
-    // usize is more dynamic than other unsigned integer types?
-    num_lines: usize,
-    num_words: usize,
-    num_bytes: usize,
-    num_chars: usize,
+// Width of the count columns, picked like GNU wc does before anything is
+// counted: wide enough for the combined size of all regular files, and
+// at least 7 wide when a pipe or terminal might bring in any amount of data
+fn column_width(config: &Config) -> usize {
+    let shown = COLUMNS.iter().filter(|column| column.shown(config)).count();
+    if config.files.len() == 1 && shown == 1 {
+        return 1;
+    }
+    let mut min_width = 1;
+    let mut regular_total = 0;
+    for filename in &config.files {
+        let metadata = match filename.as_str() {
+            "-" => io::stdin()
+                .as_fd()
+                .try_clone_to_owned()
+                .and_then(|fd| File::from(fd).metadata()),
+            _ => fs::metadata(filename),
+        };
+        match metadata {
+            Ok(metadata) if metadata.is_file() => regular_total += metadata.len(),
+            Ok(_) => min_width = 7,
+            // Reported once the file is counted
+            Err(_) => {}
+        }
+    }
+    regular_total.to_string().len().max(min_width)
 }
 
-// Possiblyr return a FileInfo struct
-// because this might involve I/O
-fn count(mut file: impl BufRead) -> MyResult<FileInfo> {
-    let mut num_lines = 0;
-    let mut num_words = 0;
-    let mut num_bytes = 0;
-    let mut num_chars = 0;
-    let mut line = String::new();
+// Print the counts of a file, or of the total when there is no name
+fn print_record(filename: Option<&str>, info: &FileInfo, config: &Config, width: usize) {
+    match config.format {
+        Format::Text => println!(
+            "{}{}",
+            format_counts(info, config, width),
+            match filename {
+                None => " total".to_string(),
+                // Stdin
+                Some("-") => "".to_string(),
+                Some(filename) => format!(" {}", filename),
+            }
+        ),
+        // The total is told apart by having no name,
+        // an empty file name is never valid
+        Format::Json => println!(
+            r#"{{"file":{},"lines":{},"words":{},"chars":{},"bytes":{}}}"#,
+            filename.map_or("null".to_string(), json_string),
+            info.num_lines,
+            info.num_words,
+            info.num_chars,
+            info.num_bytes
+        ),
+        Format::Csv => println!(
+            "{},{},{},{},{}",
+            csv_field(filename.unwrap_or("")),
+            info.num_lines,
+            info.num_words,
+            info.num_chars,
+            info.num_bytes
+        ),
+    }
+}
 
-    loop {
-        let line_bytes = file.read_line(&mut line)?;
-        if line_bytes == 0 {
-            break;
+// Quote a string for JSON output.
+// uniqr/src/main.rs has a copy of this, fix both together
+fn json_string(value: &str) -> String {
+    let mut quoted = String::from("\"");
+    for ch in value.chars() {
+        match ch {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            ch if ch.is_control() => quoted.push_str(&format!("\\u{:04x}", ch as u32)),
+            ch => quoted.push(ch),
         }
-        num_bytes += line_bytes;
-        num_lines += 1;
-        num_words += line.split_whitespace().count();
-        num_chars += line.chars().count();
-        // Prepare for next line iteration
-        line.clear();
-    }
-
-    Ok(FileInfo {
-        num_lines,
-        num_words,
-        num_bytes,
-        num_chars,
-    })
+    }
+    quoted.push('"');
+    quoted
 }
 
-fn format_field(value: usize, show: bool) -> String {
-    if show {
-        format!("{:>8}", value)
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
     } else {
-        "".to_string()
+        value.to_string()
     }
 }
 
+#[derive(Debug, Clone, Copy)]
+enum Column {
+    Lines,
+    Words,
+    Chars,
+    Bytes,
+    MaxLineLength,
+}
+
+// The order the counts are printed in, the same as GNU wc.
+// Both the per-file lines and the total line go through this table.
+const COLUMNS: [Column; 5] = [
+    Column::Lines,
+    Column::Words,
+    Column::Chars,
+    Column::Bytes,
+    Column::MaxLineLength,
+];
+
+impl Column {
+    fn shown(self, config: &Config) -> bool {
+        match self {
+            Column::Lines => config.lines,
+            Column::Words => config.words,
+            Column::Chars => config.chars,
+            Column::Bytes => config.bytes,
+            Column::MaxLineLength => config.max_line_length,
+        }
+    }
+
+    fn value(self, info: &FileInfo) -> usize {
+        match self {
+            Column::Lines => info.num_lines,
+            Column::Words => info.num_words,
+            Column::Chars => info.num_chars,
+            Column::Bytes => info.num_bytes,
+            Column::MaxLineLength => info.max_line_length,
+        }
+    }
+}
+
+// The shown counts, separated by a space like GNU wc
+fn format_counts(info: &FileInfo, config: &Config, width: usize) -> String {
+    COLUMNS
+        .iter()
+        .filter(|column| column.shown(config))
+        .map(|column| format_field(column.value(info), width))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn format_field(value: usize, width: usize) -> String {
+    format!("{:>width$}", value)
+}
+
 // Only compile when testing
 #[cfg(test)]
 // Separate module
 mod tests {
-    use super::{FileInfo, count, format_field};
-    // In-memory buffer to fake a filehandle for tests
-    // For production, use File::open
-    use std::io::Cursor;
+    use super::{csv_field, format_field, json_string};
 
     #[test]
-    fn test_count() {
-        let text = "I don't want the world. I just want your half.\r\n";
-        let info = count(Cursor::new(text));
-        assert!(info.is_ok());
-        let expected = FileInfo {
-            num_lines: 1,
-            num_words: 10,
-            num_chars: 48,
-            num_bytes: 48,
-        };
-        assert_eq!(info.unwrap(), expected);
+    fn test_format_field() {
+        assert_eq!(format_field(1, 1), "1");
+        // The spaces are necessary.
+        assert_eq!(format_field(3, 7), "      3");
+        assert_eq!(format_field(10, 7), "     10");
+        // Never cut off a count that does not fit
+        assert_eq!(format_field(12345, 3), "12345");
     }
 
     #[test]
-    fn test_format_field() {
-        assert_eq!(format_field(1, false), "");
-        // The spaces are necessary.
-        assert_eq!(format_field(3, true), "       3");
-        assert_eq!(format_field(10, true), "      10");
+    fn test_json_string() {
+        assert_eq!(json_string("fox.txt"), r#""fox.txt""#);
+        assert_eq!(json_string("a \"b\"\\c\n"), r#""a \"b\"\\c\n""#);
+        assert_eq!(json_string("\x01"), r#""\u0001""#);
+    }
+
+    #[test]
+    fn test_csv_field() {
+        assert_eq!(csv_field("fox.txt"), "fox.txt");
+        assert_eq!(csv_field("a,b"), r#""a,b""#);
+        assert_eq!(csv_field(r#"say "hi""#), r#""say ""hi""""#);
     }
 }
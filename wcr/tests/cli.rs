@@ -10,6 +10,8 @@ const EMPTY: &str = "tests/inputs/empty.txt";
 const FOX: &str = "tests/inputs/fox.txt";
 // Contain a lot of Unicode characters that require multiple bytes
 const ATLAMAL: &str = "tests/inputs/atlamal.txt";
+// Not valid UTF-8
+const INVALID: &str = "tests/inputs/invalid.txt";
 
 fn gen_bad_file() -> String {
     loop {
@@ -53,11 +55,23 @@ fn skips_bad_file() -> Result<()> {
     cargo::cargo_bin_cmd!("wcr")
         .arg(bad)
         .assert()
-        .success()
+        .failure()
         .stderr(predicate::str::is_match(expected)?);
     Ok(())
 }
 
+#[test]
+fn reports_read_error() -> Result<()> {
+    // Opening a directory works, reading it does not
+    cargo::cargo_bin_cmd!("wcr")
+        .args([FOX, "tests/inputs"])
+        .assert()
+        .failure()
+        .stdout(predicate::str::contains("tests/inputs/fox.txt"))
+        .stderr("tests/inputs: Is a directory (os error 21)\n");
+    Ok(())
+}
+
 #[test]
 fn dies_chars_and_bytes() -> Result<()> {
     // NOTE: Clap's API change, so the pattern order might not as expected
@@ -191,6 +205,12 @@ fn atlamal_bytes_lines() -> Result<()> {
     run(&["-l", "-c", ATLAMAL], "tests/expected/atlamal.txt.cl.out")
 }
 
+// --------------------------------------------------
+#[test]
+fn atlamal_max_line_length() -> Result<()> {
+    run(&["-L", ATLAMAL], "tests/expected/atlamal.txt.L.out")
+}
+
 // --------------------------------------------------
 #[test]
 fn test_all() -> Result<()> {
@@ -232,3 +252,129 @@ fn test_all_words_lines() -> Result<()> {
 fn test_all_bytes_lines() -> Result<()> {
     run(&["-cl", EMPTY, FOX, ATLAMAL], "tests/expected/all.cl.out")
 }
+
+// --------------------------------------------------
+#[test]
+fn test_all_max_line_length() -> Result<()> {
+    run(&["-L", EMPTY, FOX, ATLAMAL], "tests/expected/all.L.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn test_all_lines_chars_max_line_length() -> Result<()> {
+    run(
+        &["--max-line-length", "-lm", EMPTY, FOX, ATLAMAL],
+        "tests/expected/all.lmL.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn files0_from_file() -> Result<()> {
+    run(
+        &["--files0-from", "tests/inputs/files0.txt"],
+        "tests/expected/all.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn files0_from_stdin() -> Result<()> {
+    let expected = fs::read_to_string("tests/expected/all.out")?;
+    let list = fs::read("tests/inputs/files0.txt")?;
+    cargo::cargo_bin_cmd!("wcr")
+        .arg("--files0-from=-")
+        .write_stdin(list)
+        .assert()
+        .success()
+        .stdout(expected);
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn files0_from_reports_missing_files() -> Result<()> {
+    let bad = gen_bad_file();
+    let list = format!("{FOX}\0{bad}\0{FOX}");
+    cargo::cargo_bin_cmd!("wcr")
+        .arg("--files0-from=-")
+        .write_stdin(list)
+        .assert()
+        .failure()
+        .stdout(predicate::str::ends_with(" 2 18 96 total\n"))
+        .stderr(predicate::str::is_match(format!(
+            "{bad}: .* [(]os error 2[)]"
+        ))?);
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_files0_from_with_operands() -> Result<()> {
+    cargo::cargo_bin_cmd!("wcr")
+        .args(["--files0-from", "tests/inputs/files0.txt", FOX])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "file operands cannot be combined with --files0-from",
+        ));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn test_all_json() -> Result<()> {
+    run(
+        &["--format", "json", EMPTY, FOX, ATLAMAL],
+        "tests/expected/all.json.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn test_all_csv() -> Result<()> {
+    run(
+        &["--format", "csv", EMPTY, FOX, ATLAMAL],
+        "tests/expected/all.csv.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn fox_csv_ignores_count_flags() -> Result<()> {
+    run(
+        &["--format=csv", "-l", FOX],
+        "tests/expected/fox.txt.csv.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn invalid_utf8() -> Result<()> {
+    run(&[INVALID], "tests/expected/invalid.txt.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn invalid_utf8_chars() -> Result<()> {
+    run(&["-m", INVALID], "tests/expected/invalid.txt.m.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn word_separators() -> Result<()> {
+    let text = "a\u{a0}b c\u{3000}d\n";
+    for (args, expected) in [
+        (vec!["-w"], "4\n"),
+        (vec!["-w", "--word-separators=unicode"], "4\n"),
+        (vec!["-w", "--word-separators=ascii"], "2\n"),
+    ] {
+        cargo::cargo_bin_cmd!("wcr")
+            .args(args)
+            .write_stdin(text)
+            .assert()
+            .success()
+            .stdout(expected);
+    }
+    Ok(())
+}